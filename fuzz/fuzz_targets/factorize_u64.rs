@@ -0,0 +1,21 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use types::PrimeProduct;
+use prime_sieves::factor;
+
+fuzz_target!(|c: PrimeProduct| {
+    let mut primes = c.primes;
+    let n: u64 = primes.iter().product();
+    primes.sort_unstable();
+    let mut expected: Vec<(u64, u32)> = vec![];
+    for p in primes {
+        match expected.last_mut() {
+            Some((q, e)) if *q == p => *e += 1,
+            _ => expected.push((p, 1)),
+        }
+    }
+    {
+        let actual = factor::factorize_u64(n);
+        assert_eq!(actual, expected)
+    }
+});
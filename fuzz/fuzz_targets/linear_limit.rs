@@ -0,0 +1,8 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use types::{ConstrainedUSize, check_primes_up_to};
+use prime_sieves::sieve::LinearSieve;
+
+fuzz_target!(|c: ConstrainedUSize| {
+    check_primes_up_to(LinearSieve, c.num);
+});
@@ -0,0 +1,8 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use types::{ConstrainedN, check_first_n_primes};
+use prime_sieves::sieve::LinearSieve;
+
+fuzz_target!(|c: ConstrainedN| {
+    check_first_n_primes(LinearSieve, c.num);
+});
@@ -8,8 +8,18 @@ pub struct ConstrainedN {
     pub num: usize
 }
 
+//Up to 4 primes from the sieve whose product fits a u64, each one
+//the first prime past a random number of at most 32 bits.
+#[derive(Debug)]
+pub struct PrimeProduct {
+    pub primes: Vec<u64>
+}
+
 
 use libfuzzer_sys::arbitrary::{Arbitrary,Result,Unstructured};
+use prime_sieves::segmented::sieve_range;
+use prime_sieves::sieve::PrimeSieve;
+use primal_sieve::{Primes, Sieve};
 
 impl<'a> Arbitrary<'a> for ConstrainedUSize {
     fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
@@ -22,3 +32,36 @@ impl<'a> Arbitrary<'a> for ConstrainedN {
         u.int_in_range(0..=1270607).map(|i| ConstrainedN { num: i })
     }
 }
+
+impl<'a> Arbitrary<'a> for PrimeProduct {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut primes = vec![];
+        let mut product: u64 = 1;
+        for _ in 0..u.int_in_range(1..=4)? {
+            let bits = u.int_in_range(2..=32)?;
+            let from = u.int_in_range(1 << (bits - 1)..=(1u64 << bits) - 1)?;
+            //Gaps between primes below 2^32 stay under 512.
+            let prime = sieve_range::<u64>(from as usize, from as usize + 512).primes_result[0];
+            match product.checked_mul(prime) {
+                Some(p) => product = p,
+                None => break,
+            }
+            primes.push(prime);
+        }
+        Ok(PrimeProduct { primes })
+    }
+}
+
+//Checks for every PrimeSieve, against primal.
+pub fn check_primes_up_to<S: PrimeSieve>(sieve: S, num: usize) {
+    let vec_expected: Vec<usize> = Sieve::new(num).primes_from(0).take_while(|&n| n <= num).collect();
+    let vec_actual: Vec<usize> = sieve.primes_up_to(num);
+    assert_eq!(vec_actual[..], vec_expected[..]);
+    assert_eq!(sieve.count_up_to(num), vec_expected.len() as u64);
+}
+
+pub fn check_first_n_primes<S: PrimeSieve>(sieve: S, num: usize) {
+    let vec_expected: Vec<usize> = Primes::all().take(num).collect();
+    let vec_actual: Vec<usize> = sieve.first_n_primes(num);
+    assert_eq!(vec_actual[..], vec_expected[..]);
+}
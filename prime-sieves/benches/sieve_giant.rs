@@ -1,25 +1,29 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use prime_sieves::pritchard::*;
 use prime_sieves::segmented::*;
+use prime_sieves::sieve::*;
 use rand::distributions::Uniform;
 use rand::prelude::{Distribution, SeedableRng};
 use test_utils::FIXED_SEED;
 
 pub fn criterion_benchmark(c: &mut Criterion) {
+    eprintln!("segment size: {} bytes", SieveConfig::default().segment_bytes());
     let distribution = Uniform::from(0..2000000000);
     let rng = rand::rngs::StdRng::from_seed(FIXED_SEED);
     let vec: Vec<usize> = distribution.sample_iter(rng).take(2000).collect();
-    //Pritchard is to expensive to run on big inputs.
-    /*
-    c.bench_function("sieve_pritchard", |b| {
+    bench_sieve(c, "sieve_pritchard", PritchardSieve, &vec);
+    bench_sieve(c, "sieve_segmented", SegmentedSieve::default(), &vec);
+    #[cfg(feature = "parallel")]
+    c.bench_function("par_sieve_segmented", |b| {
         b.iter(|| {
-            apply_sieve(sieve_pritchard, vec.iter().cycle());
+            apply_sieve(par_sieve_segmented::<usize>, vec.iter().cycle());
         })
     });
-    */
-    c.bench_function("sieve_segmented", |b| {
+}
+
+fn bench_sieve<S: PrimeSieve>(c: &mut Criterion, name: &str, sieve: S, vec: &[usize]) {
+    c.bench_function(name, |b| {
         b.iter(|| {
-            apply_sieve(sieve_segmented, vec.iter().cycle());
+            apply_sieve(|n| sieve.primes_up_to::<usize>(n), vec.iter().cycle());
         })
     });
 }
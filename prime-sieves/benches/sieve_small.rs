@@ -1,22 +1,36 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use prime_sieves::pritchard::*;
 use prime_sieves::segmented::*;
+use prime_sieves::sieve::*;
 use rand::distributions::Uniform;
 use rand::prelude::{Distribution, SeedableRng};
 use test_utils::FIXED_SEED;
 
 pub fn criterion_benchmark(c: &mut Criterion) {
+    eprintln!("segment size: {} bytes", SieveConfig::default().segment_bytes());
     let distribution = Uniform::from(0..200000);
     let rng = rand::rngs::StdRng::from_seed(FIXED_SEED);
     let vec: Vec<usize> = distribution.sample_iter(rng).take(20000000).collect();
-    c.bench_function("sieve_pritchard", |b| {
+    bench_sieve(c, "sieve_pritchard", PritchardSieve, &vec);
+    bench_sieve(c, "sieve_dynamic_wheel", DynamicWheelSieve, &vec);
+    bench_sieve(c, "sieve_linear", LinearSieve, &vec);
+    bench_sieve(c, "sieve_atkin", AtkinSieve::default(), &vec);
+    bench_sieve(c, "sieve_segmented", SegmentedSieve::default(), &vec);
+    //Counting leaves out collecting the primes, so mostly the sieving itself.
+    bench_count(c, "count_segmented", SegmentedSieve::default(), &vec);
+}
+
+fn bench_sieve<S: PrimeSieve>(c: &mut Criterion, name: &str, sieve: S, vec: &[usize]) {
+    c.bench_function(name, |b| {
         b.iter(|| {
-            apply_sieve(sieve_pritchard, vec.iter().cycle());
+            apply_sieve(|n| sieve.primes_up_to::<usize>(n), vec.iter().cycle());
         })
     });
-    c.bench_function("sieve_segmented", |b| {
+}
+
+fn bench_count<S: PrimeSieve>(c: &mut Criterion, name: &str, sieve: S, vec: &[usize]) {
+    c.bench_function(name, |b| {
         b.iter(|| {
-            apply_sieve(sieve_segmented, vec.iter().cycle());
+            apply_sieve(|n| sieve.count_up_to(n), vec.iter().cycle());
         })
     });
 }
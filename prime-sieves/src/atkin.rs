@@ -0,0 +1,194 @@
+use bitvec::prelude::BitSlice;
+use std::cmp::{max, min};
+use wasm_bindgen::prelude::*;
+
+use crate::bounds::pi_upper;
+use crate::error::{
+    check_limit, check_width, first_n_via_limit, to_js, try_bitvec, try_with_capacity, OrPanic,
+    SieveError,
+};
+use crate::segmented::{try_sieve_segmented, SieveConfig};
+use crate::utils::{isqrt, PrimeInt};
+
+//Quadratic form deciding primality of each residue mod 60 coprime to it,
+//0 for the residues sharing a factor with 60.
+//1: 4x^2 + y^2, 2: 3x^2 + y^2, 3: 3x^2 - y^2 with x > y.
+const FORMS: [u8; 60] = [
+    0, 1, 0, 0, 0, 0, 0, 2, 0, 0, 0, 3, 0, 1, 0, 0, 0, 1, 0, 2, 0, 0, 0, 3, 0, 0, 0, 0, 0, 1,
+    0, 2, 0, 0, 0, 0, 0, 1, 0, 0, 0, 1, 0, 2, 0, 0, 0, 3, 0, 1, 0, 0, 0, 1, 0, 0, 0, 0, 0, 3,
+];
+const SMALL_PRIMES: [usize; 3] = [2, 3, 5];
+//The forms are evaluated a little past the end of the segment, and
+//3x^2 - y^2 at half again as much, all of it within usize up to here.
+const MAX_LIMIT: usize = usize::MAX / 4;
+
+fn ceil_sqrt(n: usize) -> usize {
+    let r = isqrt(n);
+    if r * r < n { r + 1 } else { r }
+}
+
+//Flips n in the segment starting at low if its residue goes by form.
+fn toggle(segment: &mut BitSlice, low: usize, n: usize, form: u8) {
+    if FORMS[n % 60] == form {
+        let i = n - low;
+        let bit = segment[i];
+        segment.set(i, !bit);
+    }
+}
+
+//Candidates coprime to 60 in [low, high) are prime if squarefree and
+//their form has an odd number of solutions. Every x takes the y with its
+//value in the segment, odd values only needing y of a single parity.
+fn sieve_atkin_segment(low: usize, high: usize, sieving_primes: &[usize], segment: &mut BitSlice) {
+    segment.fill(false);
+
+    //4x^2 + y^2, odd y.
+    let mut x = 1;
+    while 4 * x * x + 1 < high {
+        let fx = 4 * x * x;
+        let mut y = (if low > fx { ceil_sqrt(low - fx) } else { 1 }) | 1;
+        while fx + y * y < high {
+            toggle(segment, low, fx + y * y, 1);
+            y += 2;
+        }
+        x += 1;
+    }
+
+    //3x^2 + y^2, y of the opposite parity to x.
+    let mut x = 1;
+    while 3 * x * x + 1 < high {
+        let fx = 3 * x * x;
+        let mut y = if low > fx { max(1, ceil_sqrt(low - fx)) } else { 1 };
+        if (x + y).is_multiple_of(2) {
+            y += 1;
+        }
+        while fx + y * y < high {
+            toggle(segment, low, fx + y * y, 2);
+            y += 2;
+        }
+        x += 1;
+    }
+
+    //3x^2 - y^2 with x > y, y of the opposite parity to x. The smallest
+    //value for x is at y = x - 1, the biggest at y = 1.
+    let mut x = max(1, isqrt(low / 3));
+    while 2 * x * x + 2 * x - 1 < high {
+        let fx = 3 * x * x;
+        if fx > low {
+            let mut y = if fx >= high { isqrt(fx - high) + 1 } else { 1 };
+            if (x + y).is_multiple_of(2) {
+                y += 1;
+            }
+            let y_max = min(x - 1, isqrt(fx - low));
+            while y <= y_max {
+                toggle(segment, low, fx - y * y, 3);
+                y += 2;
+            }
+        }
+        x += 1;
+    }
+
+    //Squares of primes and their multiples toggled an odd number of times.
+    for &p in sieving_primes {
+        let square = p * p;
+        if square >= high {
+            break;
+        }
+        let first = low.div_ceil(square) * square;
+        for m in (first..high).step_by(square) {
+            segment.set(m - low, false);
+        }
+    }
+}
+
+#[wasm_bindgen]
+pub fn sieve_atkin_w(primes: usize) -> Result<JsValue, JsError> {
+    Ok(to_js(&try_sieve_atkin::<usize>(primes)?)?)
+}
+
+pub fn sieve_atkin<T: PrimeInt>(primes: usize) -> Vec<T> {
+    try_sieve_atkin(primes).or_panic()
+}
+
+pub fn try_sieve_atkin<T: PrimeInt>(primes: usize) -> Result<Vec<T>, SieveError> {
+    try_sieve_atkin_with(primes, SieveConfig::default())
+}
+
+//Segments hold a bit per number and take as many bytes as those of
+//SieveSegmented, but no fewer numbers than the square root of the limit
+//so the quadratic forms aren't swept over for too few values.
+pub fn sieve_atkin_with<T: PrimeInt>(primes: usize, config: SieveConfig) -> Vec<T> {
+    try_sieve_atkin_with(primes, config).or_panic()
+}
+
+pub fn try_sieve_atkin_with<T: PrimeInt>(
+    primes: usize, config: SieveConfig
+) -> Result<Vec<T>, SieveError> {
+    check_width::<T>("limit", primes)?;
+    check_limit(primes, MAX_LIMIT)?;
+    let mut primes_accum: Vec<T> = try_with_capacity(pi_upper(primes))?;
+    primes_accum.extend(SMALL_PRIMES.into_iter().filter(|&p| p <= primes).map(T::from_usize));
+    if primes < 7 {
+        return Ok(primes_accum);
+    }
+
+    let sqrt = isqrt(primes);
+    let sieving_primes: Vec<usize> = try_sieve_segmented::<usize>(sqrt)?
+        .primes_result
+        .into_iter()
+        .filter(|&p| p >= 7)
+        .collect();
+    let segment_size = min(max(config.segment_bytes().saturating_mul(8), sqrt), primes + 1);
+    let mut segment = try_bitvec(segment_size)?;
+
+    let mut low = 0;
+    while low <= primes {
+        let high = min(primes, low + segment_size - 1) + 1;
+        sieve_atkin_segment(low, high, &sieving_primes, &mut segment);
+        primes_accum.extend(segment[..high - low].iter_ones().map(|i| T::from_usize(low + i)));
+        low = high;
+    }
+    Ok(primes_accum)
+}
+
+#[wasm_bindgen]
+pub fn n_primes_atkin_w(primes: usize) -> Result<JsValue, JsError> {
+    Ok(to_js(&try_n_primes_atkin::<usize>(primes)?)?)
+}
+
+pub fn n_primes_atkin<T: PrimeInt>(n: usize) -> Vec<T> {
+    try_n_primes_atkin(n).or_panic()
+}
+
+pub fn try_n_primes_atkin<T: PrimeInt>(n: usize) -> Result<Vec<T>, SieveError> {
+    first_n_via_limit(n, try_sieve_atkin)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn atkin_segments() {
+        use super::sieve_atkin_with;
+        use crate::segmented::SieveConfig;
+        use primal_sieve::Sieve;
+        use test_utils::assert_primes;
+
+        //Segments as narrow as the square root of the limit.
+        let primes: Sieve = Sieve::new(1000000);
+        let config = SieveConfig::detect().with_segment_bytes(8);
+        for limit in [9999, 10000, 65536, 1000000] {
+            assert_primes(limit, &primes, |n| sieve_atkin_with(n, config));
+        }
+    }
+
+    #[test]
+    fn atkin_errors() {
+        use super::{try_sieve_atkin, MAX_LIMIT};
+        use crate::error::SieveError;
+
+        assert_eq!(
+            try_sieve_atkin::<usize>(usize::MAX),
+            Err(SieveError::LimitTooLarge { limit: usize::MAX as u64, max: MAX_LIMIT as u64 })
+        );
+    }
+}
@@ -0,0 +1,175 @@
+//Proven bounds on the prime counting function pi(x) and the nth prime p_n,
+//used to size allocations and sieving limits ahead of time.
+//Rosser & Schoenfeld, Approximate formulas for some functions of prime
+//numbers (1962), and Dusart, The kth prime is greater than k(ln k + ln ln k - 1)
+//for k >= 2 (1999) and Estimates of some functions over primes without
+//R.H. (2010).
+
+use std::cmp::min;
+
+use crate::utils::PrimeInt;
+
+//Below these the bounds are exact.
+const SMALL_PRIMES: [usize; 25] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97
+];
+const SMALL_LIMIT: usize = 100;
+
+fn small_pi(x: usize) -> usize {
+    SMALL_PRIMES.partition_point(|&p| p <= x)
+}
+
+//Floats carry plenty of precision for the slack in these bounds, casts
+//back to usize saturate where they overflow.
+fn x_over_ln(x: usize) -> (f64, f64) {
+    let x = x as f64;
+    (x / x.ln(), x.ln())
+}
+
+//pi(x) >= x / ln x for x >= 17 (Rosser & Schoenfeld),
+//pi(x) >= x / ln x * (1 + 1 / ln x) for x >= 599 (Dusart).
+pub fn pi_lower(x: usize) -> usize {
+    if x < SMALL_LIMIT {
+        return small_pi(x);
+    }
+    let (base, ln) = x_over_ln(x);
+    let bound = if x < 599 { base } else { base * (1.0 + 1.0 / ln) };
+    bound.floor() as usize
+}
+
+//pi(x) < 1.25506 * x / ln x for x > 1 (Rosser & Schoenfeld),
+//pi(x) <= x / ln x * (1 + 1 / ln x + 2.51 / ln^2 x) for x >= 355991 (Dusart).
+pub fn pi_upper(x: usize) -> usize {
+    if x < SMALL_LIMIT {
+        return small_pi(x);
+    }
+    let (base, ln) = x_over_ln(x);
+    let bound = if x < 355991 {
+        1.25506 * base
+    } else {
+        base * (1.0 + 1.0 / ln + 2.51 / (ln * ln))
+    };
+    bound.ceil() as usize
+}
+
+//Primes in [lo, hi], besides the difference of the bounds at each end
+//pi(x + y) - pi(x) < 2 * y / ln y for y >= 2 (Montgomery & Vaughan)
+//keeps narrow windows far from 0 from getting huge bounds.
+pub fn pi_range_upper(lo: usize, hi: usize) -> usize {
+    if lo > hi {
+        return 0;
+    }
+    let ends = pi_upper(hi).saturating_sub(pi_lower(lo.saturating_sub(1)));
+    let y = (hi - lo).saturating_add(1);
+    let window = if y < 2 {
+        y
+    } else {
+        let (base, _) = x_over_ln(y);
+        (2.0 * base).ceil() as usize
+    };
+    ends.min(window)
+}
+
+//p_n >= n * (ln n + ln ln n - 1) for n >= 2 (Dusart),
+//0 stands in for the nonexistent 0th prime.
+pub fn nth_prime_lower(n: usize) -> usize {
+    match n {
+        0 => 0,
+        _ if n <= SMALL_PRIMES.len() => SMALL_PRIMES[n - 1],
+        _ => {
+            let ln = (n as f64).ln();
+            (n as f64 * (ln + ln.ln() - 1.0)).floor() as usize
+        }
+    }
+}
+
+//p_n < n * (ln n + ln ln n) for n >= 6 (Rosser & Schoenfeld),
+//p_n <= n * (ln n + ln ln n - 0.9484) for n >= 39017 (Dusart).
+pub fn nth_prime_upper(n: usize) -> usize {
+    match n {
+        0 => 0,
+        _ if n <= SMALL_PRIMES.len() => SMALL_PRIMES[n - 1],
+        _ => {
+            let ln = (n as f64).ln();
+            let offset = if n < 39017 { 0.0 } else { 0.9484 };
+            (n as f64 * (ln + ln.ln() - offset)).ceil() as usize
+        }
+    }
+}
+
+//Limit the first n primes are sieved up to. Past T::MAX the nth prime
+//couldn't be output anyway.
+pub(crate) fn nth_prime_limit<T: PrimeInt>(n: usize) -> usize {
+    min(nth_prime_upper(n), T::max_usize())
+}
+
+#[cfg(test)]
+mod tests {
+    use primal_sieve::Sieve;
+
+    #[test]
+    fn pi_bounds_hold() {
+        use super::{pi_lower, pi_upper};
+
+        let sieve: Sieve = Sieve::new(10000000);
+        let mut pi = 0;
+        for x in 0..=10000000 {
+            if sieve.is_prime(x) {
+                pi += 1;
+            }
+            assert!(pi_lower(x) <= pi && pi <= pi_upper(x), "pi({}) = {}", x, pi);
+        }
+        assert_eq!(pi_lower(99), 25);
+        assert_eq!(pi_upper(99), 25);
+    }
+
+    #[test]
+    fn pi_range_bounds_hold() {
+        use super::pi_range_upper;
+        use rand::distributions::Uniform;
+        use rand::prelude::{Distribution, SeedableRng, StdRng};
+        use test_utils::FIXED_SEED;
+
+        let sieve: Sieve = Sieve::new(10000000);
+        let pi = |x: usize| sieve.prime_pi(x);
+        let los = Uniform::from(0..=9000000);
+        let widths = Uniform::from(0..=1000000);
+        let mut rng = StdRng::from_seed(FIXED_SEED);
+        for _ in 0..10000 {
+            let lo = los.sample(&mut rng);
+            let hi = lo + widths.sample(&mut rng);
+            let count = pi(hi) - if lo > 0 { pi(lo - 1) } else { 0 };
+            assert!(count <= pi_range_upper(lo, hi), "[{}, {}] holds {}", lo, hi, count);
+        }
+        for lo in 0..=1000 {
+            for hi in lo..=lo + 100 {
+                let count = pi(hi) - if lo > 0 { pi(lo - 1) } else { 0 };
+                assert!(count <= pi_range_upper(lo, hi), "[{}, {}] holds {}", lo, hi, count);
+            }
+        }
+        assert_eq!(pi_range_upper(10, 9), 0);
+        assert!(pi_range_upper(1000000000000, 1000000100000) < 20000);
+    }
+
+    #[test]
+    fn nth_prime_bounds_hold() {
+        use super::{nth_prime_lower, nth_prime_upper};
+
+        let sieve: Sieve = Sieve::new(180000000);
+        assert_eq!(nth_prime_lower(0), 0);
+        for (n, p) in (1..=10000000).zip(sieve.primes_from(0)) {
+            assert!(nth_prime_lower(n) <= p && p <= nth_prime_upper(n), "p_{} = {}", n, p);
+        }
+        assert_eq!(nth_prime_upper(25), 97);
+    }
+
+    #[test]
+    fn bounds_are_tight() {
+        use super::{nth_prime_lower, nth_prime_upper, pi_lower, pi_upper};
+
+        //Within a few percent of pi(10^9) = 50847534 and p_(10^8) = 2038074743.
+        assert!(pi_upper(1000000000) - pi_lower(1000000000) < 50847534 / 50);
+        assert!(nth_prime_upper(100000000) - nth_prime_lower(100000000) < 2038074743 / 20);
+        assert_eq!(nth_prime_upper(usize::MAX), usize::MAX);
+    }
+}
@@ -0,0 +1,138 @@
+use std::sync::OnceLock;
+
+//Data cache sizes in bytes of the cpu running the sieves, None where
+//they couldn't be detected.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheSizes {
+    pub l1d: Option<usize>,
+    pub l2: Option<usize>,
+}
+
+//Detected once and reused, sysfs takes precedence over cpuid.
+pub fn cache_sizes() -> CacheSizes {
+    static CACHE_SIZES: OnceLock<CacheSizes> = OnceLock::new();
+    *CACHE_SIZES.get_or_init(|| {
+        let sysfs = sysfs_cache_sizes();
+        let cpuid = cpuid_cache_sizes();
+        CacheSizes {
+            l1d: sysfs.l1d.or(cpuid.l1d),
+            l2: sysfs.l2.or(cpuid.l2),
+        }
+    })
+}
+
+//Sizes as written by sysfs, e.g. 48K or 2M.
+fn parse_size(size: &str) -> Option<usize> {
+    let size = size.trim();
+    let (digits, unit) = match size.char_indices().find(|(_, c)| !c.is_ascii_digit()) {
+        Some((i, _)) => size.split_at(i),
+        None => (size, ""),
+    };
+    let scale = match unit {
+        "" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        _ => return None,
+    };
+    digits.parse::<usize>().ok()?.checked_mul(scale)
+}
+
+#[cfg(target_os = "linux")]
+fn sysfs_cache_sizes() -> CacheSizes {
+    use std::fs::read_to_string;
+
+    let mut sizes = CacheSizes::default();
+    for index in 0.. {
+        let dir = format!("/sys/devices/system/cpu/cpu0/cache/index{}", index);
+        let read = |file: &str| read_to_string(format!("{}/{}", dir, file));
+        let (Ok(level), Ok(kind), Ok(size)) = (read("level"), read("type"), read("size")) else {
+            break;
+        };
+        match (level.trim(), kind.trim()) {
+            ("1", "Data") => sizes.l1d = parse_size(&size),
+            ("2", "Data" | "Unified") => sizes.l2 = parse_size(&size),
+            _ => {}
+        }
+    }
+    sizes
+}
+
+#[cfg(not(target_os = "linux"))]
+fn sysfs_cache_sizes() -> CacheSizes {
+    CacheSizes::default()
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn cpuid_cache_sizes() -> CacheSizes {
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86::{__cpuid, __cpuid_count};
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64::{__cpuid, __cpuid_count};
+
+    let mut sizes = CacheSizes::default();
+
+    //Deterministic cache parameters, one subleaf per cache (Intel).
+    if __cpuid(0).eax >= 4 {
+        for subleaf in 0.. {
+            let leaf = __cpuid_count(4, subleaf);
+            let kind = leaf.eax & 0x1f;
+            if kind == 0 {
+                break;
+            }
+            let level = (leaf.eax >> 5) & 0x7;
+            let ways = ((leaf.ebx >> 22) & 0x3ff) as usize + 1;
+            let partitions = ((leaf.ebx >> 12) & 0x3ff) as usize + 1;
+            let line = (leaf.ebx & 0xfff) as usize + 1;
+            let sets = leaf.ecx as usize + 1;
+            let size = Some(ways * partitions * line * sets);
+            match (level, kind) {
+                //1 is a data cache, 3 a unified one.
+                (1, 1) => sizes.l1d = size,
+                (2, 1 | 3) => sizes.l2 = size,
+                _ => {}
+            }
+        }
+    }
+
+    //L1 and L2 identifiers in KiB (AMD).
+    let extended = __cpuid(0x8000_0000).eax;
+    if sizes.l1d.is_none() && extended >= 0x8000_0005 {
+        let kib = (__cpuid(0x8000_0005).ecx >> 24) as usize;
+        sizes.l1d = Some(kib << 10).filter(|&size| size > 0);
+    }
+    if sizes.l2.is_none() && extended >= 0x8000_0006 {
+        let kib = (__cpuid(0x8000_0006).ecx >> 16) as usize;
+        sizes.l2 = Some(kib << 10).filter(|&size| size > 0);
+    }
+    sizes
+}
+
+#[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+fn cpuid_cache_sizes() -> CacheSizes {
+    CacheSizes::default()
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn parse_sysfs_sizes() {
+        use super::parse_size;
+
+        assert_eq!(parse_size("48K\n"), Some(48 * 1024));
+        assert_eq!(parse_size("2048K"), Some(2 * 1024 * 1024));
+        assert_eq!(parse_size("1M"), Some(1024 * 1024));
+        assert_eq!(parse_size("512"), Some(512));
+        assert_eq!(parse_size("32 KiB"), None);
+        assert_eq!(parse_size(""), None);
+    }
+
+    #[test]
+    fn detected_sizes_are_sane() {
+        use super::cache_sizes;
+
+        let sizes = cache_sizes();
+        assert!(sizes.l1d.is_none_or(|l1d| (1 << 10..1 << 24).contains(&l1d)));
+        assert!(sizes.l2.is_none_or(|l2| sizes.l1d.is_none_or(|l1d| l2 >= l1d)));
+    }
+}
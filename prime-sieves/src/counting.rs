@@ -0,0 +1,202 @@
+use std::cmp::min;
+use wasm_bindgen::prelude::*;
+
+use crate::bounds::pi_lower;
+use crate::error::{try_collect, OrPanic, SieveError};
+use crate::segmented::{try_sieve_segmented, SieveSegmented};
+use crate::utils::isqrt;
+
+#[wasm_bindgen]
+pub fn count_primes_lucy_w(primes: usize) -> Result<u64, JsError> {
+    Ok(try_count_primes_lucy(primes)?)
+}
+
+//pi(primes) using Lucy_Hedgehog's method, O(n^(3/4)) time and O(sqrt(n)) space.
+//Only the counts S(v) for the values v = primes / i are kept, split between
+//small (v <= sqrt) and large (v = primes / i for i <= sqrt).
+pub fn count_primes_lucy(primes: usize) -> u64 {
+    try_count_primes_lucy(primes).or_panic()
+}
+
+pub fn try_count_primes_lucy(primes: usize) -> Result<u64, SieveError> {
+    if primes < 2 {
+        return Ok(0);
+    }
+    let sqrt = isqrt(primes);
+
+    //S(v) starts as the count of [2, v], every number is a prime candidate.
+    let mut small: Vec<usize> = try_collect((0..sqrt + 1).map(|v| v.saturating_sub(1)))?;
+    let mut large: Vec<usize> = try_collect(
+        (0..sqrt + 1).map(|i| primes.checked_div(i).map_or(0, |v| v - 1))
+    )?;
+
+    for p in try_sieve_segmented::<usize>(sqrt)?.primes_result {
+        //pi(p - 1), the candidates below p which are all primes by now.
+        let below = small[p - 1];
+        let square = p * p;
+
+        //S(v) -= S(v / p) - pi(p - 1) for every v >= p * p,
+        //large values depend on smaller ones so those are updated first.
+        for i in 1..=min(sqrt, primes / square) {
+            let d = i * p;
+            let s = if d <= sqrt { large[d] } else { small[primes / d] };
+            large[i] -= s - below;
+        }
+        for v in (square..=sqrt).rev() {
+            small[v] -= small[v / p] - below;
+        }
+    }
+    Ok(large[1] as u64)
+}
+
+#[wasm_bindgen]
+pub fn nth_prime_w(n: usize) -> Result<usize, JsError> {
+    Ok(try_nth_prime(n)?)
+}
+
+//Cipolla's asymptotic expansion of the nth prime, off by a fraction
+//of a percent from n = 1000 on.
+fn estimate_nth_prime(n: usize) -> usize {
+    let n = n as f64;
+    let ln = n.ln();
+    let lnln = ln.ln();
+    let correction = (lnln - 2.0) / ln - (lnln * lnln - 6.0 * lnln + 11.0) / (2.0 * ln * ln);
+    (n * (ln + lnln - 1.0 + correction)) as usize
+}
+
+//The nth prime counting from nth_prime(1) = 2. The estimate is corrected
+//by counting its primes with count_primes_lucy and sieving the gap to the
+//nth prime a segment at a time, without keeping any primes around.
+pub fn nth_prime(n: usize) -> usize {
+    try_nth_prime(n).or_panic()
+}
+
+//Past pi_lower(usize::MAX) the nth prime may not fit a usize.
+pub fn try_nth_prime(n: usize) -> Result<usize, SieveError> {
+    const FIRST_PRIMES: [usize; 5] = [2, 3, 5, 7, 11];
+    if n == 0 {
+        let reason = "is not a prime index, primes are counted from 1";
+        return Err(SieveError::InvalidInput { value: 0, reason });
+    }
+    if n <= FIRST_PRIMES.len() {
+        return Ok(FIRST_PRIMES[n - 1]);
+    }
+    if n > pi_lower(usize::MAX) {
+        return Err(SieveError::Overflow { what: "prime number", value: n as u64 });
+    }
+    let estimate = estimate_nth_prime(n);
+    let count = try_count_primes_lucy(estimate)? as usize;
+
+    let mut nth = 0;
+    if count < n {
+        //Short of n, the prime is the (n - count)th one past the estimate.
+        SieveSegmented::builder()
+            .start(estimate + 1)
+            .first(n - count)
+            .try_for_each(|p| nth = p)?;
+    } else {
+        //Past n, skip the count - n biggest primes up to the estimate.
+        //Windows below it widen until they hold one more than that.
+        let skip = count - n;
+        let gap = (estimate as f64).ln() as usize + 1;
+        let mut width = 2 * gap * (skip + 1);
+        loop {
+            let lo = estimate.saturating_sub(width);
+            let found = SieveSegmented::builder().start(lo).limit(estimate).try_count()? as usize;
+            if found > skip {
+                SieveSegmented::builder()
+                    .start(lo)
+                    .first(found - skip)
+                    .try_for_each(|p| nth = p)?;
+                break;
+            }
+            width *= 2;
+        }
+    }
+    Ok(nth)
+}
+
+#[cfg(test)]
+mod tests {
+    use primal_sieve::Sieve;
+    use rand::distributions::Uniform;
+    use rand::prelude::{Distribution, SeedableRng, StdRng};
+    use test_utils::FIXED_SEED;
+
+    #[test]
+    fn lucy_against_sieve() {
+        use super::count_primes_lucy;
+        use crate::segmented::{count_primes, sieve_segmented};
+
+        let primes = sieve_segmented::<usize>(10000).primes_result;
+        for x in 0..=10000 {
+            let expected = primes.partition_point(|&p| p <= x) as u64;
+            assert_eq!(count_primes_lucy(x), expected);
+        }
+
+        for x in [1048575, 1048576, 1048577, 16777216] {
+            assert_eq!(count_primes_lucy(x), count_primes(x));
+        }
+
+        let sieve: Sieve = Sieve::new(1000000000);
+        let distribution = Uniform::from(0..=1000000000);
+        let rng = StdRng::from_seed(FIXED_SEED);
+        for x in distribution.sample_iter(rng).take(200) {
+            assert_eq!(count_primes_lucy(x), sieve.prime_pi(x) as u64);
+        }
+    }
+
+    #[test]
+    fn nth_against_sieve() {
+        use super::nth_prime;
+
+        let sieve: Sieve = Sieve::new(100000000);
+        for n in 1..=500 {
+            assert_eq!(nth_prime(n), sieve.nth_prime(n));
+        }
+
+        let distribution = Uniform::from(1..=5000000);
+        let rng = StdRng::from_seed(FIXED_SEED);
+        for n in distribution.sample_iter(rng).take(50) {
+            assert_eq!(nth_prime(n), sieve.nth_prime(n));
+        }
+    }
+
+    #[test]
+    fn nth_powers_of_ten() {
+        use super::nth_prime;
+
+        let expected = [
+            2, 29, 541, 7919, 104729, 1299709, 15485863, 179424673, 2038074743, 22801763489,
+            252097800623,
+        ];
+        for (e, p) in expected.into_iter().enumerate() {
+            assert_eq!(nth_prime(10usize.pow(e as u32)), p);
+        }
+    }
+
+    #[test]
+    fn lucy_powers_of_ten() {
+        use super::count_primes_lucy;
+
+        let expected = [
+            0, 4, 25, 168, 1229, 9592, 78498, 664579, 5761455, 50847534, 455052511,
+        ];
+        for (e, pi) in expected.into_iter().enumerate() {
+            assert_eq!(count_primes_lucy(10usize.pow(e as u32)), pi);
+        }
+    }
+
+    #[test]
+    fn nth_errors() {
+        use super::try_nth_prime;
+        use crate::error::SieveError;
+
+        assert!(matches!(try_nth_prime(0), Err(SieveError::InvalidInput { value: 0, .. })));
+        assert_eq!(
+            try_nth_prime(usize::MAX),
+            Err(SieveError::Overflow { what: "prime number", value: usize::MAX as u64 })
+        );
+        assert_eq!(try_nth_prime(1000), Ok(7919));
+    }
+}
@@ -0,0 +1,203 @@
+use std::mem::{replace, take};
+use wasm_bindgen::prelude::*;
+
+use crate::bounds::pi_upper;
+use crate::error::{
+    check_limit, check_width, first_n, to_js, try_with_capacity, OrPanic, SieveError,
+};
+use crate::segmented::{wheel_count, WHEEL_INDEX, WHEEL_PRIMES, WHEEL_SIZE};
+use crate::utils::PrimeInt;
+
+//Distance from each residue coprime to 30 to the next one, by WHEEL_INDEX.
+const WHEEL_GAPS: [usize; 8] = [6, 4, 2, 4, 2, 4, 6, 2];
+//Candidates run up to the first prime past the limit, with multiples
+//scheduled a few square roots further, all of it within usize up to here.
+const MAX_LIMIT: usize = usize::MAX / 2;
+
+//Next number coprime to 30 after w, itself coprime to 30.
+fn wheel_next(w: usize) -> usize {
+    w + WHEEL_GAPS[WHEEL_INDEX[w % WHEEL_SIZE]]
+}
+
+//Position of w amongst the numbers coprime to 30, 1 being the 0th.
+fn ordinal(w: usize) -> usize {
+    wheel_count(w) - 1
+}
+
+//Unbounded dynamic wheel, yields every prime in increasing order while
+//only keeping the primes up to the square root of the current candidate.
+//Candidates roll over the mod 30 wheel, each sieving prime p waits in the
+//bucket of its next multiple p * w with w coprime to 30 too, so a candidate
+//is prime when nothing waits on its bucket. Buckets are a circular window
+//longer than the gap between two such multiples, which is at most 6 * p
+//numbers or fewer than 2 * p candidates.
+//Sieving primes come from a nested wheel running at the square root,
+//created only once the first one past 7 is needed.
+#[derive(Debug)]
+pub struct DynamicWheel {
+    wheel_primes: std::array::IntoIter<usize, 3>,
+    candidate: usize,
+    //Ordinal of candidate, buckets are indexed by it.
+    position: usize,
+    buckets: Vec<Vec<(usize, usize)>>,
+    //Next sieving prime, added once the candidate reaches its square.
+    next_base: usize,
+    base: Option<Box<DynamicWheel>>,
+}
+
+impl DynamicWheel {
+    fn new() -> DynamicWheel {
+        DynamicWheel {
+            wheel_primes: WHEEL_PRIMES.into_iter(),
+            candidate: 7,
+            position: 1,
+            buckets: vec![Vec::new(); 16],
+            next_base: 7,
+            base: None,
+        }
+    }
+
+    //Files p under the bucket of its multiple p * w.
+    fn schedule(&mut self, p: usize, w: usize) {
+        let slot = ordinal(p * w) % self.buckets.len();
+        self.buckets[slot].push((p, w));
+    }
+
+    //Doubles the window until p strides within it, rebucketing everything.
+    fn grow(&mut self, p: usize) {
+        let mut len = self.buckets.len();
+        if len > 2 * p {
+            return;
+        }
+        while len <= 2 * p {
+            len *= 2;
+        }
+        let old = replace(&mut self.buckets, vec![Vec::new(); len]);
+        for (p, w) in old.into_iter().flatten() {
+            self.schedule(p, w);
+        }
+    }
+
+    fn next_base(&mut self) -> usize {
+        let base = self.base.get_or_insert_with(|| {
+            //Skip the wheel primes and 7, which the wheel starts with.
+            let mut base = Box::new(DynamicWheel::new());
+            base.nth(3);
+            base
+        });
+        base.next().unwrap()
+    }
+}
+
+impl Iterator for DynamicWheel {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if let Some(p) = self.wheel_primes.next() {
+            return Some(p);
+        }
+        loop {
+            let candidate = self.candidate;
+            let slot = self.position % self.buckets.len();
+
+            //Every prime waiting here divides the candidate, move them on
+            //to their next multiple. None of them lands back on this slot.
+            let mut hits = take(&mut self.buckets[slot]);
+            let composite = !hits.is_empty();
+            for (p, w) in hits.drain(..) {
+                self.schedule(p, wheel_next(w));
+            }
+            self.buckets[slot] = hits;
+
+            //A square has no smaller prime factor, so nothing waited on it.
+            let square = candidate == self.next_base * self.next_base;
+            if square {
+                let p = self.next_base;
+                self.grow(p);
+                self.schedule(p, wheel_next(p));
+                self.next_base = self.next_base();
+            }
+
+            self.candidate = wheel_next(candidate);
+            self.position += 1;
+            if !composite && !square {
+                return Some(candidate);
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
+
+pub fn dynamic_wheel() -> DynamicWheel {
+    DynamicWheel::new()
+}
+
+#[wasm_bindgen]
+pub fn sieve_dynamic_wheel_w(primes: usize) -> Result<JsValue, JsError> {
+    Ok(to_js(&try_sieve_dynamic_wheel::<usize>(primes)?)?)
+}
+
+pub fn sieve_dynamic_wheel<T: PrimeInt>(primes: usize) -> Vec<T> {
+    try_sieve_dynamic_wheel(primes).or_panic()
+}
+
+pub fn try_sieve_dynamic_wheel<T: PrimeInt>(primes: usize) -> Result<Vec<T>, SieveError> {
+    check_width::<T>("limit", primes)?;
+    check_limit(primes, MAX_LIMIT)?;
+    let mut primes_accum = try_with_capacity(pi_upper(primes))?;
+    primes_accum.extend(dynamic_wheel().take_while(|&p| p <= primes).map(T::from_usize));
+    Ok(primes_accum)
+}
+
+#[wasm_bindgen]
+pub fn n_primes_dynamic_wheel_w(primes: usize) -> Result<JsValue, JsError> {
+    Ok(to_js(&try_n_primes_dynamic_wheel::<usize>(primes)?)?)
+}
+
+pub fn n_primes_dynamic_wheel<T: PrimeInt>(n: usize) -> Vec<T> {
+    try_n_primes_dynamic_wheel(n).or_panic()
+}
+
+pub fn try_n_primes_dynamic_wheel<T: PrimeInt>(n: usize) -> Result<Vec<T>, SieveError> {
+    let mut primes_accum = try_with_capacity(n)?;
+    primes_accum.extend(
+        dynamic_wheel()
+            .take(n)
+            .take_while(|&p| T::fits(p) && p <= MAX_LIMIT)
+            .map(T::from_usize)
+    );
+    first_n(primes_accum, n)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn dynamic_wheel_state() {
+        use super::dynamic_wheel;
+
+        //Only primes up to the square root are ever held.
+        let mut wheel = dynamic_wheel();
+        assert_eq!(wheel.nth(664578), Some(9999991));
+        let held: usize = wheel.buckets.iter().map(Vec::len).sum();
+        assert_eq!(held, 446 - 3);
+        assert!(wheel.buckets.len() <= 4 * 3163);
+    }
+
+    #[test]
+    fn dynamic_wheel_errors() {
+        use super::{try_n_primes_dynamic_wheel, try_sieve_dynamic_wheel, MAX_LIMIT};
+        use crate::error::SieveError;
+
+        assert_eq!(
+            try_sieve_dynamic_wheel::<usize>(usize::MAX),
+            Err(SieveError::LimitTooLarge { limit: usize::MAX as u64, max: MAX_LIMIT as u64 })
+        );
+        assert!(matches!(
+            try_n_primes_dynamic_wheel::<usize>(usize::MAX),
+            Err(SieveError::AllocationFailure { .. })
+        ));
+    }
+}
@@ -0,0 +1,170 @@
+use bitvec::vec::BitVec;
+use serde::Serialize;
+use std::error::Error;
+use std::fmt;
+use std::mem::size_of;
+use wasm_bindgen::JsValue;
+
+use crate::bounds::nth_prime_limit;
+use crate::utils::PrimeInt;
+
+//Everything the try_ functions can fail on. Their plain counterparts
+//panic with the Display of these, wasm exports throw them as JS errors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SieveError {
+    //A limit, prime or table entry past what the output type holds.
+    Overflow { what: &'static str, value: u64 },
+    //A limit past the biggest one the algorithm is safe up to.
+    LimitTooLarge { limit: u64, max: u64 },
+    //A table or result too big to allocate.
+    AllocationFailure { bytes: usize },
+    //An argument the function isn't defined for, such as factorizing 0.
+    InvalidInput { value: u64, reason: &'static str },
+    //A result that couldn't be turned into a JsValue.
+    Serialization(String),
+}
+
+impl fmt::Display for SieveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SieveError::Overflow { what, value } => {
+                write!(f, "{} {} overflows the output width", what, value)
+            }
+            SieveError::LimitTooLarge { limit, max } => {
+                write!(f, "limit {} is past the biggest supported limit {}", limit, max)
+            }
+            SieveError::AllocationFailure { bytes } => {
+                write!(f, "failed to allocate {} bytes", bytes)
+            }
+            SieveError::InvalidInput { value, reason } => write!(f, "{} {}", value, reason),
+            SieveError::Serialization(message) => write!(f, "failed to serialize: {}", message),
+        }
+    }
+}
+
+impl Error for SieveError {}
+
+impl From<serde_wasm_bindgen::Error> for SieveError {
+    fn from(error: serde_wasm_bindgen::Error) -> SieveError {
+        SieveError::Serialization(error.to_string())
+    }
+}
+
+//The panicking functions are their try_ counterparts unwrapped.
+pub(crate) trait OrPanic<T> {
+    fn or_panic(self) -> T;
+}
+
+impl<T> OrPanic<T> for Result<T, SieveError> {
+    #[track_caller]
+    fn or_panic(self) -> T {
+        self.unwrap_or_else(|error| panic!("{}", error))
+    }
+}
+
+pub(crate) fn to_js<T: Serialize + ?Sized>(value: &T) -> Result<JsValue, SieveError> {
+    Ok(serde_wasm_bindgen::to_value(value)?)
+}
+
+pub(crate) fn check_width<T: PrimeInt>(what: &'static str, value: usize) -> Result<(), SieveError> {
+    match T::fits(value) {
+        true => Ok(()),
+        false => Err(SieveError::Overflow { what, value: value as u64 }),
+    }
+}
+
+//The first n of primes sieved up to an upper bound of the nth prime,
+//cut short by the output width if they're fewer.
+pub(crate) fn first_n<T>(mut primes: Vec<T>, n: usize) -> Result<Vec<T>, SieveError> {
+    if primes.len() < n {
+        return Err(SieveError::Overflow { what: "prime number", value: n as u64 });
+    }
+    primes.truncate(n);
+    Ok(primes)
+}
+
+//The first n primes out of sieve run up to nth_prime_limit.
+pub(crate) fn first_n_via_limit<T, F>(n: usize, sieve: F) -> Result<Vec<T>, SieveError>
+where T: PrimeInt, F: FnOnce(usize) -> Result<Vec<T>, SieveError> {
+    first_n(sieve(nth_prime_limit::<T>(n))?, n)
+}
+
+pub(crate) fn check_limit(limit: usize, max: usize) -> Result<(), SieveError> {
+    match limit <= max {
+        true => Ok(()),
+        false => Err(SieveError::LimitTooLarge { limit: limit as u64, max: max as u64 }),
+    }
+}
+
+//Grows amortized, empty vecs get exactly what they ask for.
+pub(crate) fn try_reserve<T>(vec: &mut Vec<T>, additional: usize) -> Result<(), SieveError> {
+    vec.try_reserve(additional).map_err(|_| SieveError::AllocationFailure {
+        bytes: additional.saturating_mul(size_of::<T>()),
+    })
+}
+
+pub(crate) fn try_with_capacity<T>(capacity: usize) -> Result<Vec<T>, SieveError> {
+    let mut vec = vec![];
+    try_reserve(&mut vec, capacity)?;
+    Ok(vec)
+}
+
+//vec![value; len] without aborting when it doesn't fit in memory.
+pub(crate) fn try_vec<T: Clone>(value: T, len: usize) -> Result<Vec<T>, SieveError> {
+    let mut vec = try_with_capacity(len)?;
+    vec.resize(len, value);
+    Ok(vec)
+}
+
+//Collects an iterator of known length without aborting.
+pub(crate) fn try_collect<T, I>(iter: I) -> Result<Vec<T>, SieveError>
+where I: ExactSizeIterator<Item = T> {
+    let mut vec = try_with_capacity(iter.len())?;
+    vec.extend(iter);
+    Ok(vec)
+}
+
+//Cleared BitVec of the given length, allocated a word at a time.
+pub(crate) fn try_bitvec(bits: usize) -> Result<BitVec, SieveError> {
+    let mut bitvec = BitVec::from_vec(try_vec(0usize, bits.div_ceil(usize::BITS as usize))?);
+    bitvec.truncate(bits);
+    Ok(bitvec)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{first_n, try_bitvec, try_vec, OrPanic, SieveError};
+
+    #[test]
+    fn error_messages() {
+        let overflow = SieveError::Overflow { what: "limit", value: 4294967296 };
+        assert_eq!(overflow.to_string(), "limit 4294967296 overflows the output width");
+        let too_large = SieveError::LimitTooLarge { limit: 10, max: 9 };
+        assert_eq!(too_large.to_string(), "limit 10 is past the biggest supported limit 9");
+        let invalid = SieveError::InvalidInput { value: 0, reason: "has no prime factorization" };
+        assert_eq!(invalid.to_string(), "0 has no prime factorization");
+
+        assert_eq!(first_n(vec![2, 3, 5], 2), Ok(vec![2, 3]));
+        assert_eq!(
+            first_n(vec![2, 3, 5], 4),
+            Err(SieveError::Overflow { what: "prime number", value: 4 })
+        );
+    }
+
+    #[test]
+    fn error_allocation() {
+        //More than isize::MAX bytes never reaches the allocator.
+        assert_eq!(
+            try_vec(0u32, usize::MAX / 2),
+            Err(SieveError::AllocationFailure { bytes: usize::MAX })
+        );
+        assert!(try_vec(0u32, 1000).is_ok_and(|vec| vec.len() == 1000));
+        assert!(try_bitvec(1000).is_ok_and(|bits| bits.len() == 1000 && bits.not_any()));
+    }
+
+    #[test]
+    #[should_panic(expected = "failed to allocate")]
+    fn error_or_panic() {
+        try_vec(0u64, usize::MAX).or_panic();
+    }
+}
@@ -0,0 +1,444 @@
+use serde::Serialize;
+use std::cmp::min;
+use wasm_bindgen::prelude::*;
+
+use crate::error::{to_js, OrPanic, SieveError};
+use crate::linear::linear_sieve;
+use crate::primality::{is_prime_u64, trial_primes};
+use crate::segmented::{try_sieve_range, try_sieve_segmented};
+use crate::utils::isqrt;
+
+//Smallest prime factor of every number up to a limit, filled in by a
+//linear sieve which visits each composite exactly once as i * p with p
+//its smallest prime factor. Primes are marked 0 so factors, which never
+//exceed the square root of the limit, fit in a u32.
+#[derive(Debug)]
+pub struct SpfTable {
+    pub limit: usize,
+    spf: Vec<u32>,
+    pub primes: Vec<usize>,
+}
+
+impl SpfTable {
+    fn new(limit: usize) -> Result<SpfTable, SieveError> {
+        let (spf, primes) = linear_sieve(limit, |_, _| {})?;
+        Ok(SpfTable { limit, spf, primes })
+    }
+
+    //Smallest prime factor of 2 <= n <= limit.
+    pub fn smallest_factor(&self, n: usize) -> usize {
+        self.try_smallest_factor(n).or_panic()
+    }
+
+    pub fn try_smallest_factor(&self, n: usize) -> Result<usize, SieveError> {
+        if n < 2 {
+            let reason = "has no smallest prime factor";
+            return Err(SieveError::InvalidInput { value: n as u64, reason });
+        }
+        if n > self.limit {
+            return Err(SieveError::LimitTooLarge { limit: n as u64, max: self.limit as u64 });
+        }
+        match self.spf[n] {
+            0 => Ok(n),
+            p => Ok(p as usize),
+        }
+    }
+
+    //Prime powers of n in increasing order of the primes, empty for 1.
+    //Within the table it takes a lookup per prime factor, past it trial
+    //division by the table primes, then by further sieved primes if
+    //those run out before reaching the square root, brings n into it.
+    pub fn factorize(&self, n: usize) -> Vec<(usize, u32)> {
+        self.try_factorize(n).or_panic()
+    }
+
+    pub fn try_factorize(&self, n: usize) -> Result<Vec<(usize, u32)>, SieveError> {
+        check_factorizable(n as u64)?;
+        let mut factors = vec![];
+        let mut n = n;
+        if n > self.limit {
+            n = trial_division(n, &self.primes, &mut factors);
+            if n > self.limit && self.primes.last().is_none_or(|&p| p.saturating_mul(p) < n) {
+                let lo = self.primes.last().map_or(0, |&p| p + 1);
+                let primes = try_sieve_range::<usize>(lo, isqrt(n))?.primes_result;
+                n = trial_division(n, &primes, &mut factors);
+            }
+            if n > self.limit {
+                factors.push((n, 1));
+                return Ok(factors);
+            }
+        }
+        while n > 1 {
+            let p = self.try_smallest_factor(n)?;
+            let mut exponent = 0;
+            while n.is_multiple_of(p) {
+                n /= p;
+                exponent += 1;
+            }
+            push_factor(&mut factors, p, exponent);
+        }
+        Ok(factors)
+    }
+}
+
+fn check_factorizable(n: u64) -> Result<(), SieveError> {
+    match n {
+        0 => Err(SieveError::InvalidInput { value: 0, reason: "has no prime factorization" }),
+        _ => Ok(()),
+    }
+}
+
+//A prime may already have been divided out by trial division.
+fn push_factor(factors: &mut Vec<(usize, u32)>, p: usize, exponent: u32) {
+    match factors.last_mut() {
+        Some((q, e)) if *q == p => *e += exponent,
+        _ => factors.push((p, exponent)),
+    }
+}
+
+//Divides out the primes in order while they stay below the square root
+//of what is left of n, which is returned.
+fn trial_division(mut n: usize, primes: &[usize], factors: &mut Vec<(usize, u32)>) -> usize {
+    for &p in primes {
+        if p.saturating_mul(p) > n {
+            break;
+        }
+        let mut exponent = 0;
+        while n.is_multiple_of(p) {
+            n /= p;
+            exponent += 1;
+        }
+        if exponent > 0 {
+            push_factor(factors, p, exponent);
+        }
+    }
+    n
+}
+
+pub fn spf_table(limit: usize) -> SpfTable {
+    try_spf_table(limit).or_panic()
+}
+
+pub fn try_spf_table(limit: usize) -> Result<SpfTable, SieveError> {
+    SpfTable::new(limit)
+}
+
+#[wasm_bindgen]
+pub fn factorize_w(n: usize) -> Result<JsValue, JsError> {
+    Ok(to_js(&try_factorize(n)?)?)
+}
+
+//One off factorization by trial division with the primes up to the
+//square root of n, build an SpfTable to factorize many numbers.
+pub fn factorize(n: usize) -> Vec<(usize, u32)> {
+    try_factorize(n).or_panic()
+}
+
+pub fn try_factorize(n: usize) -> Result<Vec<(usize, u32)>, SieveError> {
+    check_factorizable(n as u64)?;
+    let mut factors = vec![];
+    let primes = try_sieve_segmented::<usize>(isqrt(n))?.primes_result;
+    let n = trial_division(n, &primes, &mut factors);
+    if n > 1 {
+        factors.push((n, 1));
+    }
+    Ok(factors)
+}
+
+//Arithmetic mod an odd n on values scaled by R = 2^64, a product is
+//reduced by a couple of multiplications instead of a 128 bit division.
+#[derive(Debug, Clone, Copy)]
+struct Montgomery {
+    n: u64,
+    //n^-1 mod R.
+    inv: u64,
+    //R^2 mod n, moves values into Montgomery form.
+    r2: u64,
+}
+
+impl Montgomery {
+    fn new(n: u64) -> Montgomery {
+        //Newton's iteration doubles the correct low bits, an odd n is
+        //its own inverse mod 8.
+        let mut inv = n;
+        for _ in 0..5 {
+            inv = inv.wrapping_mul(2u64.wrapping_sub(n.wrapping_mul(inv)));
+        }
+        let r = ((1u128 << 64) % n as u128) as u64;
+        let r2 = (r as u128 * r as u128 % n as u128) as u64;
+        Montgomery { n, inv, r2 }
+    }
+
+    //t * R^-1 mod n for t < n * R.
+    fn reduce(&self, t: u128) -> u64 {
+        let m = (t as u64).wrapping_mul(self.inv);
+        let mn = ((m as u128 * self.n as u128) >> 64) as u64;
+        let hi = (t >> 64) as u64;
+        let (r, borrow) = hi.overflowing_sub(mn);
+        if borrow { r.wrapping_add(self.n) } else { r }
+    }
+
+    fn mul(&self, a: u64, b: u64) -> u64 {
+        self.reduce(a as u128 * b as u128)
+    }
+
+    //a * R mod n.
+    fn enter(&self, a: u64) -> u64 {
+        self.mul(a % self.n, self.r2)
+    }
+
+    fn add(&self, a: u64, b: u64) -> u64 {
+        let (r, carry) = a.overflowing_add(b);
+        if carry || r >= self.n { r.wrapping_sub(self.n) } else { r }
+    }
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+//Brent's variant of Pollard's rho on x^2 + c for an odd composite n,
+//differences are batched into a single gcd every BATCH steps. None when
+//the cycle closed without splitting n, another c may then work.
+fn pollard_brent(n: u64, c: u64) -> Option<u64> {
+    const BATCH: u64 = 128;
+    let mont = Montgomery::new(n);
+    let c = mont.enter(c);
+    let f = |x: u64| mont.add(mont.mul(x, x), c);
+
+    let mut y = mont.enter(2);
+    let mut x = y;
+    let mut ys = y;
+    let mut q = mont.enter(1);
+    let mut g = 1;
+    let mut r = 1;
+    while g == 1 {
+        x = y;
+        for _ in 0..r {
+            y = f(y);
+        }
+        let mut k = 0;
+        while k < r && g == 1 {
+            ys = y;
+            for _ in 0..min(BATCH, r - k) {
+                y = f(y);
+                q = mont.mul(q, x.abs_diff(y));
+            }
+            //q is scaled by R, which is coprime to n.
+            g = gcd(q, n);
+            k += BATCH;
+        }
+        r *= 2;
+    }
+    if g == n {
+        //The batch overshot, step through it one difference at a time.
+        loop {
+            ys = f(ys);
+            g = gcd(x.abs_diff(ys), n);
+            if g > 1 {
+                break;
+            }
+        }
+    }
+    (g != n).then_some(g)
+}
+
+//Some nontrivial factor of an odd composite n.
+fn split(n: u64) -> u64 {
+    (1..).find_map(|c| pollard_brent(n, c)).unwrap()
+}
+
+#[wasm_bindgen]
+pub fn factorize_u64_w(n: u64) -> Result<JsValue, JsError> {
+    let serializer =
+        serde_wasm_bindgen::Serializer::new().serialize_large_number_types_as_bigints(true);
+    let factors = try_factorize_u64(n)?.serialize(&serializer).map_err(SieveError::from)?;
+    Ok(factors)
+}
+
+//Prime powers of any 64 bit n in increasing order of the primes, empty
+//for 1. Small primes go by trial division, what is left is split with
+//Pollard-Brent rho until every part passes is_prime_u64.
+pub fn factorize_u64(n: u64) -> Vec<(u64, u32)> {
+    try_factorize_u64(n).or_panic()
+}
+
+pub fn try_factorize_u64(n: u64) -> Result<Vec<(u64, u32)>, SieveError> {
+    check_factorizable(n)?;
+    let mut factors = vec![];
+    let mut n = n;
+    for &p in trial_primes() {
+        if p * p > n {
+            break;
+        }
+        let mut exponent = 0;
+        while n.is_multiple_of(p) {
+            n /= p;
+            exponent += 1;
+        }
+        if exponent > 0 {
+            factors.push((p, exponent));
+        }
+    }
+
+    //Parts left have no factor below the trial primes, so they're odd.
+    let mut large = vec![];
+    let mut parts = vec![n];
+    while let Some(part) = parts.pop() {
+        if part == 1 {
+            continue;
+        }
+        if is_prime_u64(part) {
+            large.push(part);
+        } else {
+            let d = split(part);
+            parts.push(d);
+            parts.push(part / d);
+        }
+    }
+    large.sort_unstable();
+    for p in large {
+        match factors.last_mut() {
+            Some((q, e)) if *q == p => *e += 1,
+            _ => factors.push((p, 1)),
+        }
+    }
+    Ok(factors)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::primality::is_prime_u64;
+    use rand::distributions::Uniform;
+    use rand::prelude::{Distribution, SeedableRng, StdRng};
+    use test_utils::FIXED_SEED;
+
+    fn assert_factorization(n: usize, factors: &[(usize, u32)]) {
+        assert!(factors.windows(2).all(|w| w[0].0 < w[1].0), "{}: {:?}", n, factors);
+        let prime_powers = factors.iter().all(|&(p, e)| e > 0 && is_prime_u64(p as u64));
+        assert!(prime_powers, "{}: {:?}", n, factors);
+        let product: usize = factors.iter().map(|&(p, e)| p.pow(e)).product();
+        assert_eq!(product, n);
+    }
+
+    #[test]
+    fn spf_against_sieve() {
+        use super::spf_table;
+        use primal_sieve::Sieve;
+
+        let sieve: Sieve = Sieve::new(100000);
+        let table = spf_table(100000);
+        assert!(table.primes.iter().copied().eq(sieve.primes_from(0).take_while(|&p| p <= 100000)));
+        for n in 2..=100000 {
+            let expected = sieve.factor(n).unwrap()[0].0;
+            assert_eq!(table.smallest_factor(n), expected);
+        }
+        assert!(spf_table(0).primes.is_empty());
+        assert!(spf_table(1).primes.is_empty());
+        assert_eq!(spf_table(2).primes, vec![2]);
+    }
+
+    #[test]
+    fn factorize_in_table() {
+        use super::{factorize, spf_table};
+
+        let table = spf_table(100000);
+        assert!(table.factorize(1).is_empty());
+        for n in 2..=100000 {
+            let factors = table.factorize(n);
+            assert_factorization(n, &factors);
+            if n % 97 == 0 {
+                assert_eq!(factors, factorize(n));
+            }
+        }
+        assert_eq!(table.factorize(65536), vec![(2, 16)]);
+        assert_eq!(table.factorize(99991), vec![(99991, 1)]);
+    }
+
+    #[test]
+    fn factorize_past_table() {
+        use super::{factorize, spf_table};
+
+        let table = spf_table(1000);
+        let distribution = Uniform::from(1..=1000000000000);
+        let rng = StdRng::from_seed(FIXED_SEED);
+        for n in distribution.sample_iter(rng).take(50) {
+            let factors = table.factorize(n);
+            assert_factorization(n, &factors);
+            assert_eq!(factors, factorize(n));
+        }
+        //Primes past the table, alone and squared.
+        assert_eq!(table.factorize(999983 * 999979), vec![(999979, 1), (999983, 1)]);
+        assert_eq!(table.factorize(2 * 1009 * 1009), vec![(2, 1), (1009, 2)]);
+        assert_eq!(table.factorize(1000003), vec![(1000003, 1)]);
+        assert_eq!(spf_table(10).factorize(1024 * 49), vec![(2, 10), (7, 2)]);
+    }
+
+    #[test]
+    fn factorize_u64_small() {
+        use super::{factorize, factorize_u64};
+
+        assert!(factorize_u64(1).is_empty());
+        for n in 2..=20000usize {
+            let expected: Vec<(u64, u32)> =
+                factorize(n).into_iter().map(|(p, e)| (p as u64, e)).collect();
+            assert_eq!(factorize_u64(n as u64), expected);
+        }
+    }
+
+    #[test]
+    fn factorize_u64_products() {
+        use super::factorize_u64;
+        use crate::segmented::sieve_range;
+        use std::cmp::Ordering;
+
+        //Primes from just below 2^32 and around 2^20, the former pair into
+        //the hardest semiprimes for rho.
+        let large = sieve_range::<u64>(4294960000, 4294967295).primes_result;
+        let medium = sieve_range::<u64>(1048000, 1048576).primes_result;
+        let distribution = Uniform::from(0..large.len());
+        let rng = StdRng::from_seed(FIXED_SEED);
+        let mut picks = distribution.sample_iter(rng);
+        for _ in 0..50 {
+            let (p, q) = (large[picks.next().unwrap()], large[picks.next().unwrap()]);
+            let expected = match p.cmp(&q) {
+                Ordering::Equal => vec![(p, 2)],
+                _ => vec![(p.min(q), 1), (p.max(q), 1)],
+            };
+            assert_eq!(factorize_u64(p * q), expected);
+
+            let r = medium[picks.next().unwrap() % medium.len()];
+            assert_eq!(factorize_u64(3 * r * r * r), vec![(3, 1), (r, 3)]);
+        }
+
+        let fermat = vec![(3, 1), (5, 1), (17, 1), (257, 1), (641, 1), (65537, 1), (6700417, 1)];
+        assert_eq!(factorize_u64(u64::MAX), fermat);
+        assert_eq!(factorize_u64(1 << 63), vec![(2, 63)]);
+        assert_eq!(factorize_u64(18446744073709551557), vec![(18446744073709551557, 1)]);
+        //A strong pseudoprime to the first several prime bases.
+        assert_eq!(factorize_u64(3825123056546413051), vec![(149491, 1), (747451, 1), (34233211, 1)]);
+    }
+
+    #[test]
+    fn factor_errors() {
+        use super::{spf_table, try_factorize, try_factorize_u64};
+        use crate::error::SieveError;
+
+        let zero = SieveError::InvalidInput { value: 0, reason: "has no prime factorization" };
+        assert_eq!(try_factorize(0), Err(zero.clone()));
+        assert_eq!(try_factorize_u64(0), Err(zero.clone()));
+        assert_eq!(try_factorize(12), Ok(vec![(2, 2), (3, 1)]));
+
+        let table = spf_table(100);
+        assert_eq!(table.try_factorize(0), Err(zero));
+        assert_eq!(table.try_smallest_factor(91), Ok(7));
+        assert!(matches!(table.try_smallest_factor(1), Err(SieveError::InvalidInput { .. })));
+        assert_eq!(
+            table.try_smallest_factor(101),
+            Err(SieveError::LimitTooLarge { limit: 101, max: 100 })
+        );
+    }
+}
@@ -1,5 +1,16 @@
+pub mod atkin;
+pub mod bounds;
+pub mod cache;
+pub mod counting;
+pub mod dynamic_wheel;
+pub mod error;
+pub mod factor;
+pub mod linear;
+pub mod multiplicative;
+pub mod primality;
 pub mod pritchard;
 pub mod segmented;
+pub mod sieve;
 pub mod utils;
 
 
@@ -0,0 +1,102 @@
+use wasm_bindgen::prelude::*;
+
+use crate::bounds::pi_upper;
+use crate::error::{
+    check_width, first_n_via_limit, to_js, try_vec, try_with_capacity, OrPanic, SieveError,
+};
+use crate::utils::PrimeInt;
+
+//Euler's linear sieve up to limit, returns the smallest prime factor of
+//every number, 0 for primes as in SpfTable, and the primes.
+//visit(i, p) is called once for each n = i * p in increasing order of i,
+//p being the smallest prime factor of n so n / p was visited before.
+//Primes themselves come as (1, p).
+pub(crate) fn linear_sieve<F>(
+    limit: usize, mut visit: F
+) -> Result<(Vec<u32>, Vec<usize>), SieveError>
+where F: FnMut(usize, usize) {
+    let mut spf = try_vec(0u32, limit.saturating_add(1))?;
+    let mut primes = try_with_capacity(pi_upper(limit))?;
+    for i in 2..=limit {
+        let lowest = match spf[i] {
+            0 => {
+                primes.push(i);
+                visit(1, i);
+                i
+            }
+            p => p as usize,
+        };
+        for &p in primes.iter().take_while(|&&p| p <= lowest) {
+            let Some(multiple) = i.checked_mul(p).filter(|&m| m <= limit) else {
+                break;
+            };
+            spf[multiple] = p as u32;
+            visit(i, p);
+        }
+    }
+    Ok((spf, primes))
+}
+
+
+#[wasm_bindgen]
+pub fn sieve_linear_w(primes: usize) -> Result<JsValue, JsError> {
+    Ok(to_js(&try_sieve_linear::<usize>(primes)?)?)
+}
+
+//Every composite is crossed off exactly once, by its smallest prime
+//factor, at the cost of a u32 per number for the factors.
+pub fn sieve_linear<T: PrimeInt>(primes: usize) -> Vec<T> {
+    try_sieve_linear(primes).or_panic()
+}
+
+pub fn try_sieve_linear<T: PrimeInt>(primes: usize) -> Result<Vec<T>, SieveError> {
+    check_width::<T>("limit", primes)?;
+    let (_, primes_accum) = linear_sieve(primes, |_, _| {})?;
+    Ok(primes_accum.into_iter().map(T::from_usize).collect())
+}
+
+#[wasm_bindgen]
+pub fn n_primes_linear_w(primes: usize) -> Result<JsValue, JsError> {
+    Ok(to_js(&try_n_primes_linear::<usize>(primes)?)?)
+}
+
+pub fn n_primes_linear<T: PrimeInt>(n: usize) -> Vec<T> {
+    try_n_primes_linear(n).or_panic()
+}
+
+pub fn try_n_primes_linear<T: PrimeInt>(n: usize) -> Result<Vec<T>, SieveError> {
+    first_n_via_limit(n, try_sieve_linear)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn linear_visits_once() {
+        use super::linear_sieve;
+
+        //Every number in [2, limit] turns up exactly once, by its smallest factor.
+        let limit = 20000;
+        let mut seen = vec![0; limit + 1];
+        let (spf, primes) = linear_sieve(limit, |i, p| {
+            seen[i * p] += 1;
+            assert!(i == 1 || p <= i && spf_of(i) >= p);
+        }).unwrap();
+        assert!(seen[2..].iter().all(|&s| s == 1));
+        assert_eq!(spf.iter().filter(|&&p| p == 0).count() - 2, primes.len());
+
+        fn spf_of(n: usize) -> usize {
+            (2..=n).find(|&d| n.is_multiple_of(d)).unwrap()
+        }
+    }
+
+    #[test]
+    fn linear_errors() {
+        use super::try_sieve_linear;
+        use crate::error::SieveError;
+
+        assert!(matches!(
+            try_sieve_linear::<usize>(usize::MAX / 4),
+            Err(SieveError::AllocationFailure { .. })
+        ));
+    }
+}
@@ -0,0 +1,290 @@
+use serde::Serialize;
+use std::cmp::{max, min};
+use std::mem::size_of;
+use wasm_bindgen::prelude::*;
+
+use crate::error::{to_js, try_reserve, try_vec, try_with_capacity, OrPanic, SieveError};
+use crate::linear::linear_sieve;
+use crate::segmented::{try_sieve_segmented, SieveConfig};
+use crate::utils::isqrt;
+
+//Euler's totient phi, Moebius mu, number of divisors tau and sum of
+//divisors sigma of every n in [lo, lo + len), entry n - lo holds n.
+//All of them are taken as 0 at n = 0.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MultiplicativeTables {
+    pub lo: usize,
+    pub phi: Vec<u64>,
+    pub mu: Vec<i8>,
+    pub tau: Vec<u32>,
+    pub sigma: Vec<u64>,
+}
+
+impl MultiplicativeTables {
+    //Every function is 1 at 1 and starts off as a product over no primes.
+    fn ones(lo: usize, len: usize) -> Result<MultiplicativeTables, SieveError> {
+        let mut tables = MultiplicativeTables {
+            lo,
+            phi: try_vec(1, len)?,
+            mu: try_vec(1, len)?,
+            tau: try_vec(1, len)?,
+            sigma: try_vec(1, len)?,
+        };
+        if lo == 0 && len > 0 {
+            tables.phi[0] = 0;
+            tables.mu[0] = 0;
+            tables.tau[0] = 0;
+            tables.sigma[0] = 0;
+        }
+        Ok(tables)
+    }
+
+    //Multiplies entry i by each function at pe = p^e, sigma_pe being
+    //1 + p + ... + p^e. Only sigma can outgrow a u64, by a small factor
+    //for n close to 2^64.
+    fn apply(
+        &mut self, i: usize, p: usize, e: u32, pe: usize, sigma_pe: Option<u64>
+    ) -> Result<(), SieveError> {
+        //Most prime factors are single, sparing the division.
+        let phi_pe = if e == 1 { p - 1 } else { pe / p * (p - 1) };
+        self.phi[i] *= phi_pe as u64;
+        self.mu[i] = if e == 1 { -self.mu[i] } else { 0 };
+        self.tau[i] *= e + 1;
+        self.sigma[i] = sigma_pe.and_then(|s| self.sigma[i].checked_mul(s)).ok_or(
+            SieveError::Overflow { what: "sigma of", value: (self.lo + i) as u64 }
+        )?;
+        Ok(())
+    }
+}
+
+#[wasm_bindgen]
+pub fn multiplicative_tables_w(limit: usize) -> Result<JsValue, JsError> {
+    Ok(to_js(&try_multiplicative_tables(limit)?)?)
+}
+
+pub fn multiplicative_tables(limit: usize) -> MultiplicativeTables {
+    try_multiplicative_tables(limit).or_panic()
+}
+
+//Tables for [0, limit] in a single pass of the linear sieve. Each n = i * p
+//comes with its smallest prime p, which either is new to i so every function
+//multiplies by its value at p, or divides i too. Then n splits into the
+//power of p it holds, tracked in power, and a coprime rest, both smaller
+//than n unless n is a prime power.
+pub fn try_multiplicative_tables(limit: usize) -> Result<MultiplicativeTables, SieveError> {
+    let len = limit.saturating_add(1);
+    let mut tables = MultiplicativeTables::ones(0, len)?;
+    let MultiplicativeTables { phi, mu, tau, sigma, .. } = &mut tables;
+    let mut power = try_vec(0usize, len)?;
+    linear_sieve(limit, |i, p| {
+        let n = i * p;
+        if !i.is_multiple_of(p) {
+            phi[n] = phi[i] * (p as u64 - 1);
+            mu[n] = -mu[i];
+            tau[n] = tau[i] * 2;
+            sigma[n] = sigma[i] * (p as u64 + 1);
+            power[n] = p;
+        } else {
+            power[n] = power[i] * p;
+            phi[n] = phi[i] * p as u64;
+            mu[n] = 0;
+            let rest = n / power[n];
+            if rest == 1 {
+                tau[n] = tau[i] + 1;
+                sigma[n] = sigma[i] + n as u64;
+            } else {
+                tau[n] = tau[rest] * tau[power[n]];
+                sigma[n] = sigma[rest] * sigma[power[n]];
+            }
+        }
+    })?;
+    Ok(tables)
+}
+
+#[wasm_bindgen]
+pub fn multiplicative_range_w(lo: usize, hi: usize) -> Result<JsValue, JsError> {
+    Ok(to_js(&try_multiplicative_range(lo, hi)?)?)
+}
+
+pub fn multiplicative_range(lo: usize, hi: usize) -> MultiplicativeTables {
+    try_multiplicative_range(lo, hi).or_panic()
+}
+
+pub fn try_multiplicative_range(lo: usize, hi: usize) -> Result<MultiplicativeTables, SieveError> {
+    try_multiplicative_range_with(lo, hi, SieveConfig::default())
+}
+
+pub fn multiplicative_range_with(
+    lo: usize, hi: usize, config: SieveConfig
+) -> MultiplicativeTables {
+    try_multiplicative_range_with(lo, hi, config).or_panic()
+}
+
+//Tables for [lo, hi] a segment at a time, each sieving prime up to the
+//square root of the segment's end divides its power out of the unfactored
+//rest of its multiples. Whatever is left over is 1 or a single prime past
+//that square root. Segments hold as many entries of rest as fit in the
+//configured segment bytes. As in SieveSegmented, sieving primes join once
+//the segments reach their squares and carry the offset of their next
+//multiple from one segment to the next.
+pub fn try_multiplicative_range_with(
+    lo: usize, hi: usize, config: SieveConfig
+) -> Result<MultiplicativeTables, SieveError> {
+    if lo > hi {
+        return MultiplicativeTables::ones(lo, 0);
+    }
+    let mut tables = MultiplicativeTables::ones(lo, (hi - lo).saturating_add(1))?;
+    let primes = try_sieve_segmented::<usize>(isqrt(hi))?.primes_result;
+    let segment_size = max(config.segment_bytes() / size_of::<usize>(), 1);
+    let mut rest = try_with_capacity(min(segment_size, (hi - lo).saturating_add(1)))?;
+
+    //0 has nothing to factor.
+    let mut low = max(lo, 1);
+    let mut multiples: Vec<usize> = vec![];
+    while low <= hi {
+        let high = min(hi, low.saturating_add(segment_size - 1));
+        let len = high - low + 1;
+        rest.clear();
+        rest.extend(low..=high);
+        let sqrt_high = isqrt(high);
+        while let Some(&p) = primes.get(multiples.len()).filter(|&&p| p <= sqrt_high) {
+            //Offsets from low, the multiples themselves may not fit near usize::MAX.
+            try_reserve(&mut multiples, 1)?;
+            multiples.push((p - low % p) % p);
+        }
+        for (&p, offset) in primes.iter().zip(multiples.iter_mut()) {
+            let mut j = *offset;
+            while j < len {
+                //low + j is a multiple of p, so its rest is too.
+                let r = &mut rest[j];
+                *r /= p;
+                let (mut e, mut pe, mut sigma_pe) = (1, p, (p as u64).checked_add(1));
+                while r.is_multiple_of(p) {
+                    *r /= p;
+                    e += 1;
+                    pe *= p;
+                    sigma_pe = sigma_pe.and_then(|s| s.checked_add(pe as u64));
+                }
+                tables.apply(low - lo + j, p, e, pe, sigma_pe)?;
+                j += p;
+            }
+            *offset = j - len;
+        }
+        for (n, &q) in (low..=high).zip(&rest) {
+            if q > 1 {
+                tables.apply(n - lo, q, 1, q, (q as u64).checked_add(1))?;
+            }
+        }
+        if high == usize::MAX {
+            break;
+        }
+        low = high + 1;
+    }
+    Ok(tables)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MultiplicativeTables;
+    use crate::factor::factorize_u64;
+
+    //The four functions from the factorization of n.
+    fn by_factors(n: usize) -> (u64, i8, u32, u64) {
+        if n == 0 {
+            return (0, 0, 0, 0);
+        }
+        let factors = factorize_u64(n as u64);
+        let phi = factors.iter().map(|&(p, e)| p.pow(e - 1) * (p - 1)).product();
+        let mu = match factors.iter().all(|&(_, e)| e == 1) {
+            true if factors.len().is_multiple_of(2) => 1,
+            true => -1,
+            false => 0,
+        };
+        let tau = factors.iter().map(|&(_, e)| e + 1).product();
+        let sigma = factors
+            .iter()
+            .map(|&(p, e)| (0..=e).map(|k| p.pow(k)).sum::<u64>())
+            .product();
+        (phi, mu, tau, sigma)
+    }
+
+    fn assert_tables(tables: &MultiplicativeTables, lo: usize, hi: usize) {
+        assert_eq!(tables.lo, lo);
+        assert_eq!(tables.phi.len(), hi - lo + 1);
+        for n in lo..=hi {
+            let i = n - tables.lo;
+            let actual = (tables.phi[i], tables.mu[i], tables.tau[i], tables.sigma[i]);
+            assert_eq!(actual, by_factors(n), "{}", n);
+        }
+    }
+
+    #[test]
+    fn multiplicative_linear() {
+        use super::multiplicative_tables;
+
+        assert_tables(&multiplicative_tables(100000), 0, 100000);
+        assert_tables(&multiplicative_tables(0), 0, 0);
+        assert_tables(&multiplicative_tables(1), 0, 1);
+        assert_tables(&multiplicative_tables(2), 0, 2);
+
+        let tables = multiplicative_tables(12);
+        assert_eq!(tables.phi[12], 4);
+        assert_eq!(tables.mu[6], 1);
+        assert_eq!(tables.tau[12], 6);
+        assert_eq!(tables.sigma[12], 28);
+    }
+
+    #[test]
+    fn multiplicative_segmented() {
+        use super::{multiplicative_range, multiplicative_range_with, multiplicative_tables};
+        use crate::segmented::SieveConfig;
+
+        let tables = multiplicative_tables(20000);
+        //Many small segments against the linear tables.
+        let config = SieveConfig::detect().with_segment_bytes(64);
+        for (lo, hi) in [(0, 20000), (1, 1), (2, 2), (9973, 10007), (12345, 20000)] {
+            let range = multiplicative_range_with(lo, hi, config);
+            assert_eq!(range.phi[..], tables.phi[lo..=hi]);
+            assert_eq!(range.mu[..], tables.mu[lo..=hi]);
+            assert_eq!(range.tau[..], tables.tau[lo..=hi]);
+            assert_eq!(range.sigma[..], tables.sigma[lo..=hi]);
+        }
+        assert!(multiplicative_range(10, 9).phi.is_empty());
+
+        let lo = 1000000000000;
+        assert_tables(&multiplicative_range(lo, lo + 2000), lo, lo + 2000);
+    }
+
+    #[test]
+    #[ignore = "sieves every prime below 2^32, run with --release -- --ignored"]
+    fn multiplicative_segmented_top() {
+        use super::{multiplicative_range, try_multiplicative_range};
+        use crate::error::SieveError;
+
+        //Most sieving primes have no multiple left below usize::MAX, sigma
+        //of the biggest prime still fits.
+        let lo = usize::MAX - 58;
+        assert_tables(&multiplicative_range(lo, lo), lo, lo);
+        //Up to usize::MAX sigma of most numbers is past a u64.
+        assert!(matches!(
+            try_multiplicative_range(usize::MAX - 100, usize::MAX),
+            Err(SieveError::Overflow { what: "sigma of", .. })
+        ));
+    }
+
+    #[test]
+    fn multiplicative_errors() {
+        use super::{try_multiplicative_range, try_multiplicative_tables};
+        use crate::error::SieveError;
+
+        assert!(matches!(
+            try_multiplicative_tables(usize::MAX / 2),
+            Err(SieveError::AllocationFailure { .. })
+        ));
+        assert!(matches!(
+            try_multiplicative_range(0, usize::MAX / 2),
+            Err(SieveError::AllocationFailure { .. })
+        ));
+        assert_eq!(try_multiplicative_range(12, 12).unwrap().sigma, vec![28]);
+    }
+}
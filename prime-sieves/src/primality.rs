@@ -0,0 +1,173 @@
+use std::sync::OnceLock;
+use wasm_bindgen::prelude::*;
+
+use crate::segmented::sieve_segmented;
+
+//Trial division by the primes below this weeds out most composites
+//before the far more expensive Miller-Rabin rounds.
+const TRIAL_LIMIT: usize = 1000;
+
+//Bases making Miller-Rabin deterministic for every n < 2^64 (Sinclair).
+const MILLER_RABIN_BASES: [u64; 7] = [2, 325, 9375, 28178, 450775, 9780504, 1795265022];
+
+//Biggest prime fitting a u64, there is no next_prime past it.
+const LAST_PRIME: u64 = 18446744073709551557;
+
+pub(crate) fn trial_primes() -> &'static [u64] {
+    static TRIAL_PRIMES: OnceLock<Vec<u64>> = OnceLock::new();
+    TRIAL_PRIMES.get_or_init(|| sieve_segmented::<u64>(TRIAL_LIMIT).primes_result)
+}
+
+fn mul_mod(a: u64, b: u64, m: u64) -> u64 {
+    (a as u128 * b as u128 % m as u128) as u64
+}
+
+fn pow_mod(mut base: u64, mut exp: u64, m: u64) -> u64 {
+    let mut result = 1;
+    base %= m;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul_mod(result, base, m);
+        }
+        base = mul_mod(base, base, m);
+        exp >>= 1;
+    }
+    result
+}
+
+//Whether odd n > 2 is a strong probable prime to base a,
+//with n - 1 = d * 2^s and d odd.
+fn strong_probable_prime(n: u64, d: u64, s: u32, a: u64) -> bool {
+    let a = a % n;
+    if a == 0 {
+        return true;
+    }
+    let mut x = pow_mod(a, d, n);
+    if x == 1 || x == n - 1 {
+        return true;
+    }
+    for _ in 1..s {
+        x = mul_mod(x, x, n);
+        if x == n - 1 {
+            return true;
+        }
+    }
+    false
+}
+
+#[wasm_bindgen]
+pub fn is_prime_u64_w(n: u64) -> bool {
+    is_prime_u64(n)
+}
+
+pub fn is_prime_u64(n: u64) -> bool {
+    for &p in trial_primes() {
+        if p * p > n {
+            return n >= 2;
+        }
+        if n.is_multiple_of(p) {
+            return n == p;
+        }
+    }
+    let s = (n - 1).trailing_zeros();
+    let d = (n - 1) >> s;
+    MILLER_RABIN_BASES.iter().all(|&a| strong_probable_prime(n, d, s, a))
+}
+
+#[wasm_bindgen]
+pub fn next_prime_w(n: u64) -> Option<u64> {
+    next_prime(n)
+}
+
+//Smallest prime bigger than n, None past the last u64 prime.
+pub fn next_prime(n: u64) -> Option<u64> {
+    if n < 2 {
+        return Some(2);
+    }
+    if n >= LAST_PRIME {
+        return None;
+    }
+    //Odd candidates only, the last prime guarantees no overflow.
+    let mut candidate = (n + 1) | 1;
+    while !is_prime_u64(candidate) {
+        candidate += 2;
+    }
+    Some(candidate)
+}
+
+#[wasm_bindgen]
+pub fn prev_prime_w(n: u64) -> Option<u64> {
+    prev_prime(n)
+}
+
+//Biggest prime smaller than n, None for n <= 2.
+pub fn prev_prime(n: u64) -> Option<u64> {
+    match n {
+        0..=2 => None,
+        3 => Some(2),
+        _ => {
+            let mut candidate = (n - 2) | 1;
+            while !is_prime_u64(candidate) {
+                candidate -= 2;
+            }
+            Some(candidate)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use primal_sieve::Sieve;
+
+    #[test]
+    fn primality_against_sieve() {
+        use super::{is_prime_u64, next_prime, prev_prime};
+
+        let sieve: Sieve = Sieve::new(2000000);
+        for n in 0..=2000000 {
+            assert_eq!(is_prime_u64(n as u64), sieve.is_prime(n), "{}", n);
+        }
+        let primes: Vec<u64> = sieve.primes_from(0).map(|p| p as u64).collect();
+        for n in 0..=1000000 {
+            let next = primes[primes.partition_point(|&p| p <= n)];
+            assert_eq!(next_prime(n), Some(next));
+            let prev = primes.partition_point(|&p| p < n).checked_sub(1).map(|i| primes[i]);
+            assert_eq!(prev_prime(n), prev);
+        }
+    }
+
+    #[test]
+    fn primality_far() {
+        use super::{is_prime_u64, next_prime, prev_prime};
+        use crate::segmented::sieve_range;
+
+        let lo = 1000000000000;
+        let hi = lo + 100000;
+        let primes = sieve_range::<usize>(lo, hi).primes_result;
+        let tested: Vec<usize> = (lo..=hi).filter(|&n| is_prime_u64(n as u64)).collect();
+        assert_eq!(tested, primes);
+        assert_eq!(next_prime(lo as u64), Some(primes[0] as u64));
+        assert_eq!(prev_prime(hi as u64 + 1), Some(*primes.last().unwrap() as u64));
+    }
+
+    #[test]
+    fn primality_pseudoprimes() {
+        use super::{is_prime_u64, next_prime, prev_prime, LAST_PRIME};
+
+        //Carmichael numbers and strong pseudoprimes to several prime bases.
+        let composites = [
+            561, 1105, 2047, 1373653, 25326001, 3215031751, 2152302898747, 3474749660383,
+            341550071728321, 3825123056546413051, 4611686014132420609, u64::MAX,
+        ];
+        for n in composites {
+            assert!(!is_prime_u64(n), "{}", n);
+        }
+        let primes = [(1 << 61) - 1, 4611686018427387847, 9223372036854775783, LAST_PRIME];
+        for p in primes {
+            assert!(is_prime_u64(p), "{}", p);
+        }
+        assert_eq!(next_prime(LAST_PRIME - 1), Some(LAST_PRIME));
+        assert_eq!(next_prime(LAST_PRIME), None);
+        assert_eq!(prev_prime(u64::MAX), Some(LAST_PRIME));
+    }
+}
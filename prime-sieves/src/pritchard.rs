@@ -1,83 +1,113 @@
+use bitvec::prelude::BitSlice;
 use std::cmp::min;
 use wasm_bindgen::prelude::*;
 
-use crate::utils::{approximate_primes, isqrt};
+use crate::bounds::pi_upper;
+use crate::error::{
+    check_width, first_n_via_limit, to_js, try_bitvec, try_reserve, OrPanic, SieveError,
+};
+use crate::utils::{isqrt, PrimeInt};
 
 #[wasm_bindgen]
-pub fn sieve_pritchard_w(primes: usize) -> JsValue {
-    JsValue::from_serde(&sieve_pritchard(primes)).unwrap()
+pub fn sieve_pritchard_w(primes: usize) -> Result<JsValue, JsError> {
+    Ok(to_js(&try_sieve_pritchard::<usize>(primes)?)?)
 }
 
-pub fn sieve_pritchard(primes: usize) -> Vec<usize> {
-    let mut start_primes: Vec<usize> = vec![2, 3];
+pub fn sieve_pritchard<T: PrimeInt>(primes: usize) -> Vec<T> {
+    try_sieve_pritchard(primes).or_panic()
+}
+
+pub fn try_sieve_pritchard<T: PrimeInt>(primes: usize) -> Result<Vec<T>, SieveError> {
+    check_width::<T>("limit", primes)?;
+    let mut start_primes: Vec<T> = vec![T::from_usize(2), T::from_usize(3)];
     match primes {
         0 | 1 => {
             start_primes.clear();
-            start_primes
+            Ok(start_primes)
         }
         2 => {
             start_primes.remove(1);
-            start_primes
+            Ok(start_primes)
         }
-        3 | 4 => start_primes,
+        3 | 4 => Ok(start_primes),
         _ => {
-            let mut wheel = Vec::<usize>::with_capacity(isqrt(primes));
-            wheel.push(1);
-            wheel.push(5);
+            //The wheel only ever holds odd numbers, bit i stands for 2i + 1.
+            let mut wheel = try_bitvec(primes.div_ceil(2))?;
+            wheel.set(index(1), true);
+            wheel.set(index(5), true);
             let mut length: usize = min(primes, 6);
             let mut p = 5;
+            let sqrt = isqrt(primes);
             let mut primes_accum = start_primes;
-            while p * p <= primes {
+            let additional = pi_upper(primes) - primes_accum.len();
+            try_reserve(&mut primes_accum, additional)?;
+            while p <= sqrt {
                 if length < primes {
-                    length = extend(&mut wheel, length, min(primes, p * length));
+                    length = extend(&mut wheel, length, min(primes, p.saturating_mul(length)));
                 }
-                delete(&mut wheel, p);
-                primes_accum.push(p);
-                p = wheel[1];
+                delete(&mut wheel, length, p);
+                primes_accum.push(T::from_usize(p));
+                p = match wheel[1..].first_one() {
+                    Some(i) => 2 * (i + 1) + 1,
+                    None => break,
+                };
             }
             if length < primes {
                 extend(&mut wheel, length, primes);
             }
             union(&mut primes_accum, &wheel);
-            primes_accum
+            Ok(primes_accum)
         }
     }
 }
 
 #[wasm_bindgen]
-pub fn n_primes_pritchard_w(primes: usize) -> JsValue {
-    JsValue::from_serde(&n_primes_pritchard(primes)).unwrap()
+pub fn n_primes_pritchard_w(primes: usize) -> Result<JsValue, JsError> {
+    Ok(to_js(&try_n_primes_pritchard::<usize>(primes)?)?)
+}
+
+pub fn n_primes_pritchard<T: PrimeInt>(n: usize) -> Vec<T> {
+    try_n_primes_pritchard(n).or_panic()
 }
 
-pub fn n_primes_pritchard(n: usize) -> Vec<usize> {
-    let approx = approximate_primes(n);
+pub fn try_n_primes_pritchard<T: PrimeInt>(n: usize) -> Result<Vec<T>, SieveError> {
+    first_n_via_limit(n, try_sieve_pritchard)
+}
 
-    let mut primes_accum = sieve_pritchard(approx);
-    primes_accum.drain(n..);
-    primes_accum
+fn index(w: usize) -> usize {
+    (w - 1) / 2
 }
 
-fn extend(wheel: &mut Vec<usize>, length: usize, n: usize) -> usize {
-    let mut i = 1;
-    let mut w;
-    let mut x = length + 1;
-    while x <= n {
-        wheel.push(x);
-        w = wheel[i];
-        x = w + length;
-        i += 1;
+//Rolls the wheel from length up to n, every w + k * length is a member
+//for w in the wheel, so the bits of the first turn are just copied over
+//doubling the copied span each time.
+fn extend(wheel: &mut BitSlice, length: usize, n: usize) -> usize {
+    //length is even while extending, so a turn is a whole number of bits.
+    let turn = length / 2;
+    let end = n.div_ceil(2);
+    let mut filled = turn;
+    while filled < end {
+        let span = min(filled, end - filled);
+        wheel.copy_within(0..span, filled);
+        filled += span;
     }
     n
 }
 
-fn delete(wheel: &mut Vec<usize>, p: usize) {
-    wheel.retain(|w| w % p != 0);
+//Removes p * w for every w in the wheel up to length / p, going down
+//from the biggest w so deletions never hit a w still to be visited.
+//Only the multiples themselves are touched.
+fn delete(wheel: &mut BitSlice, length: usize, p: usize) {
+    let mut end = (length / p).div_ceil(2);
+    while let Some(i) = wheel[..end].last_one() {
+        let w = 2 * i + 1;
+        wheel.set(index(p * w), false);
+        end = i;
+    }
 }
 
-fn union(prk: &mut Vec<usize>, wheel: &[usize]) {
-    for i in &wheel[1..] {
-        prk.push(*i);
-    }
+fn union<T: PrimeInt>(prk: &mut Vec<T>, wheel: &BitSlice) {
+    prk.extend(wheel.iter_ones().skip(1).map(|i| T::from_usize(2 * i + 1)));
 }
 
 #[cfg(test)]
@@ -110,5 +140,4 @@ mod tests {
         assert_n(3, &primes, n_primes_pritchard);
         assert_n(303, &primes, n_primes_pritchard);
     }
-
 }
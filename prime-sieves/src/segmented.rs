@@ -1,190 +1,895 @@
+use bitvec::boxed::BitBox;
+use bitvec::domain::Domain;
 use bitvec::prelude::{BitSlice};
 use bitvec::vec::BitVec;
 use wasm_bindgen::prelude::*;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 use std::cmp::min;
 use std::cmp::max;
+use std::fmt::Debug;
+use std::iter::Peekable;
+use std::marker::PhantomData;
+use std::mem::size_of;
+use std::ops::ControlFlow;
+use std::sync::OnceLock;
 
-use crate::utils::approximate_primes;
+use crate::cache::cache_sizes;
+use crate::bounds::{nth_prime_limit, nth_prime_upper, pi_range_upper, pi_upper};
+use crate::error::{check_width, first_n, to_js, try_reserve, try_vec, OrPanic, SieveError};
 use crate::utils::isqrt;
+use crate::utils::PrimeInt;
 
 
-//Approximate size of L1 Cache in bits x2 because
-//even numbers are not stored.
-const L1D_CACHE_SIZE: usize = 32768 * 8 * 2;
+//Approximate size of L1 Cache in bits, used when it can't be detected.
+const L1D_CACHE_SIZE: usize = 32768 * 8;
+
+//Segments only store numbers coprime to 30, one byte holds
+//the 8 residues below of a span of 30 numbers.
+pub(crate) const WHEEL_SIZE: usize = 30;
+pub(crate) const WHEEL: [usize; 8] = [1, 7, 11, 13, 17, 19, 23, 29];
+pub(crate) const WHEEL_PRIMES: [usize; 3] = [2, 3, 5];
+//Bit of each residue within its byte, only defined for those in WHEEL.
+pub(crate) const WHEEL_INDEX: [usize; WHEEL_SIZE] = [
+    0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 2, 0, 3, 0, 0, 0, 4, 0, 5, 0, 0, 0, 6, 0, 0, 0, 0, 0, 7
+];
+//Amount of residues in WHEEL not bigger than each residue.
+const WHEEL_COUNT: [usize; WHEEL_SIZE] = [
+    0, 1, 1, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 4, 4, 4, 4, 5, 5, 6, 6, 6, 6, 7, 7, 7, 7, 7, 7, 8
+];
+//Numbers spanned by a single word of a segment.
+const WORD_SPAN: usize = usize::BITS as usize / 8 * WHEEL_SIZE;
+const WORD_BYTES: usize = size_of::<usize>();
+
+//The smallest primes past the wheel cross off the most, their multiples
+//instead come from copying a pattern repeating every PRESIEVE_BYTES bytes.
+const PRESIEVE_PRIMES: [usize; 4] = [7, 11, 13, 17];
+const PRESIEVE_BYTES: usize = 7 * 11 * 13 * 17;
+//Sieving primes start right after the presieved ones.
+const FIRST_SIEVING_PRIME: usize = 19;
+
+//Amount of numbers coprime to 30 in [0, n].
+pub(crate) fn wheel_count(n: usize) -> usize {
+    n / WHEEL_SIZE * 8 + WHEEL_COUNT[n % WHEEL_SIZE]
+}
+
+//Number held by bit i of a segment starting at low.
+fn wheel_value(low: usize, i: usize) -> usize {
+    low + i / 8 * WHEEL_SIZE + WHEEL[i % 8]
+}
+
+//Segment bytes from 0 with the multiples of the presieve primes cleared,
+//a word longer than the period so no word copied from it wraps around.
+fn presieve_pattern() -> &'static [u8] {
+    static PRESIEVE_PATTERN: OnceLock<Vec<u8>> = OnceLock::new();
+    PRESIEVE_PATTERN.get_or_init(|| {
+        (0..PRESIEVE_BYTES + WORD_BYTES)
+            .map(|byte| {
+                WHEEL
+                    .iter()
+                    .filter(|&&r| {
+                        PRESIEVE_PRIMES.iter().all(|&p| !(byte * WHEEL_SIZE + r).is_multiple_of(p))
+                    })
+                    .fold(0, |bits, &r| bits | 1 << WHEEL_INDEX[r])
+            })
+            .collect()
+    })
+}
+
+//Tunables for SieveSegmented, the default sizes segments to the
+//L1 data cache of the running cpu.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SieveConfig {
+    segment_bytes: usize,
+}
+
+impl SieveConfig {
+    pub fn detect() -> SieveConfig {
+        SieveConfig {
+            segment_bytes: cache_sizes().l1d.unwrap_or(L1D_CACHE_SIZE / 8),
+        }
+    }
+
+    //Bytes of memory per segment, rounded up to whole words.
+    pub fn with_segment_bytes(self, segment_bytes: usize) -> SieveConfig {
+        SieveConfig { segment_bytes }
+    }
+
+    pub fn segment_bytes(&self) -> usize {
+        self.segment_bytes.clamp(1, usize::MAX / WHEEL_SIZE).next_multiple_of(WORD_SPAN / WHEEL_SIZE)
+    }
+}
+
+impl Default for SieveConfig {
+    fn default() -> SieveConfig {
+        SieveConfig::detect()
+    }
+}
+
+//Next multiple of a large sieving prime in one of its progressions, as the
+//bit it clears in the segment it falls in.
+#[derive(Debug, Clone, Copy)]
+struct Bucketed {
+    prime: usize,
+    bit: usize,
+}
 
 #[derive(Debug)]
-pub struct SieveSegmented {
+pub struct SieveSegmented<T: PrimeInt = usize> {
+    start: usize,
     primes: usize,
-    sqrt: usize,
     count: usize,
     segment_size: usize,
-    is_prime: Vec<bool>,
+    //Source of the sieving primes, which are taken as the segments reach
+    //their squares. None where the wheel primes are all there is to it.
+    sieving_primes: Option<Box<Peekable<PrimeIter>>>,
     primes_vec: Vec<usize>,
-    multiples: Vec<usize>,
-    pub primes_result: Vec<usize>
+    multiples: Vec<[usize; 8]>,
+    //Sieving primes of at least a segment's bytes hit a segment at most
+    //once per progression, most of them not at all. Each progression waits
+    //in the bucket of the next segment it hits instead, a ring of them
+    //long enough for the biggest prime with buckets[bucket] the current one.
+    buckets: Vec<Vec<Bucketed>>,
+    bucket: usize,
+    pub primes_result: Vec<T>
 }
 
 impl SieveSegmented {
-    fn empty() -> SieveSegmented {
+    pub fn builder() -> SieveSegmentedBuilder {
+        SieveSegmentedBuilder {
+            bound: Bound::Limit(0),
+            start: 0,
+            config: SieveConfig::default(),
+            width: PhantomData,
+        }
+    }
+}
+
+impl<T: PrimeInt> SieveSegmented<T> {
+    fn empty() -> SieveSegmented<T> {
         SieveSegmented {
+            start: 0,
             primes: 0,
-            sqrt: 0,
             count: 0,
             segment_size: 0,
-            is_prime: vec![],
+            sieving_primes: None,
             primes_vec: vec![],
             multiples: vec![],
+            buckets: vec![],
+            bucket: 0,
             primes_result: vec![],
         }
     }
 
-    fn single() -> SieveSegmented {
+    fn single() -> SieveSegmented<T> {
         SieveSegmented {
+            start: 0,
             primes: 1,
-            sqrt: 1,
             count: 1,
             segment_size: 0,
-            is_prime: vec![],
+            sieving_primes: None,
             primes_vec: vec![],
             multiples: vec![],
-            primes_result: vec![2],
+            buckets: vec![],
+            bucket: 0,
+            primes_result: vec![T::from_usize(2)],
         } 
     }
 
-    fn new_n(n: usize) -> SieveSegmented {
-        let primes = approximate_primes(n);
-        let sqrt: usize = isqrt(primes);
-        SieveSegmented { 
-            primes,
-            sqrt,
-            count: 0,
-            segment_size: max(sqrt, L1D_CACHE_SIZE),
-            is_prime: vec!(true; sqrt + 1), 
-            primes_vec: Vec::with_capacity(sqrt), 
-            multiples: Vec::with_capacity(sqrt),
-            primes_result: Vec::with_capacity(n * 5 / 4)
-        }
+    fn new_n(n: usize, config: SieveConfig) -> Result<SieveSegmented<T>, SieveError> {
+        SieveSegmented::new(nth_prime_limit::<T>(n), config)
+    }
+
+    //At least sqrt so every sieving prime hits a segment at most once per
+    //stride, but no bigger than needed to hold [start, primes] in one segment.
+    //Always a whole number of words for the bitslice backing it.
+    fn fit_segment_size(start: usize, primes: usize, config: SieveConfig) -> usize {
+        let span = config.segment_bytes().saturating_mul(WHEEL_SIZE);
+        let range = (primes - min(start, primes)).saturating_add(1 + start % WHEEL_SIZE);
+        min(max(isqrt(primes), span), range)
+            .checked_next_multiple_of(WORD_SPAN)
+            .unwrap_or(usize::MAX / WORD_SPAN * WORD_SPAN)
     }
 
-    fn new(primes: usize) -> SieveSegmented {
+    //Sieving primes come from a sieve of their own up to the square root,
+    //so only the ones still to hit a segment are held on to. Near the top
+    //of usize narrow ranges take few of the 2^32 primes below it.
+    fn new(primes: usize, config: SieveConfig) -> Result<SieveSegmented<T>, SieveError> {
+        check_width::<T>("limit", primes)?;
         let sqrt: usize = isqrt(primes);
-        SieveSegmented {
+        let sieving_primes = match sqrt {
+            0..=6 => None,
+            _ => Some(Box::new(PrimeIter::new(sqrt)?.peekable())),
+        };
+        Ok(SieveSegmented {
+            start: 0,
             primes,
-            sqrt,
             count: 0,
-            segment_size: max(sqrt, L1D_CACHE_SIZE),
-            is_prime: vec!(true; sqrt + 1),
-            primes_vec: Vec::with_capacity(sqrt),
-            multiples: Vec::with_capacity(sqrt),
-            primes_result: Vec::with_capacity(sqrt)
-        }
-       
+            segment_size: Self::fit_segment_size(0, primes, config),
+            sieving_primes,
+            primes_vec: vec![],
+            multiples: vec![],
+            buckets: vec![],
+            bucket: 0,
+            primes_result: vec![]
+        })
     }
 
-    fn calculate_primes_for_segment(
-        &mut self, low: usize, mut i: usize, mut s: usize, 
-        sieve_segment: &mut BitSlice
-    ) -> (usize, usize) {
-    
+    fn new_range(
+        start: usize, primes: usize, config: SieveConfig
+    ) -> Result<SieveSegmented<T>, SieveError> {
+        Ok(SieveSegmented {
+            start,
+            segment_size: Self::fit_segment_size(start, primes, config),
+            ..SieveSegmented::new(primes, config)?
+        })
+    }
+
+    //Multiples p * q of p with q coprime to 30 fall in 8 progressions, one
+    //per residue of q, each advancing p bytes at a fixed bit of the byte.
+    //Byte offset from low of the first multiple not below max(p * p, low)
+    //in each progression, segments start on multiples of 30.
+    //p * q itself may not fit near usize::MAX, only its offset from low.
+    fn first_multiples(p: usize, low: usize) -> [usize; 8] {
+        let (q, offset) = match low.div_ceil(p) {
+            q if q > p => (q, (p - low % p) % p),
+            _ => (p, p * p - low),
+        };
+        WHEEL.map(|r| {
+            let step = (r + WHEEL_SIZE - q % WHEEL_SIZE) % WHEEL_SIZE;
+            (offset + p * step) / WHEEL_SIZE
+        })
+    }
+
+    //Primes dividing the wheel that lie in [start, primes].
+    fn wheel_primes(&self) -> impl Iterator<Item = usize> + '_ {
+        WHEEL_PRIMES.into_iter().filter(|&p| self.start <= p && p <= self.primes)
+    }
+
+    //Bytes of memory each segment took, as chosen from its SieveConfig.
+    pub fn segment_bytes(&self) -> usize {
+        self.segment_size / WHEEL_SIZE
+    }
+
+    fn new_segment(&self) -> Result<BitBox, SieveError> {
+        let bits = self.segment_bytes() * 8;
+        let vec = try_vec(usize::MAX, bits / (usize::BITS as usize))?;
+        let sieve_segment = BitVec::from_vec(vec).into_boxed_bitslice();
+        assert_eq!(sieve_segment.len(), bits);
+        Ok(sieve_segment)
+    }
+
+    //Last number of the segment starting at low, bounded by self.primes.
+    fn segment_high(&self, low: usize) -> usize {
+        min(low.saturating_add(self.segment_size - 1), self.primes)
+    }
+
+    //Number of candidates in the segment starting at low,
+    //bounded by self.primes.
+    fn segment_len(&self, low: usize) -> usize {
+        wheel_count(self.segment_high(low) - low)
+    }
+
+    fn sieve_segment(&mut self, low: usize, sieve_segment: &mut BitSlice) -> Result<(), SieveError> {
+
         // current segment = [low, high]
-        let high = min(low + self.segment_size - 1, self.primes);
-
-        // generate sieving primes using simple sieve of Eratosthenes
-        while i * i <= high {
-            if self.is_prime[i] {
-                let mut j = i * i;
-                while j <= self.sqrt {
-                    self.is_prime[j] = false;
-                    j += i;
+        let high = self.segment_high(low);
+
+        self.reset_segment(low, sieve_segment);
+
+        // take the sieving primes up to the square root of high, 2, 3 and 5
+        // are left out by the wheel
+        let segment_bytes = self.segment_bytes();
+        let last_byte = (self.primes - low) / WHEEL_SIZE;
+        if let Some(sieving_primes) = &mut self.sieving_primes {
+            let sqrt_high = isqrt(high);
+            while let Some(p) = sieving_primes.next_if(|&p| p <= sqrt_high) {
+                //Primes with no multiple up to the limit never cross anything off.
+                if p < FIRST_SIEVING_PRIME || (p - low % p) % p > self.primes - low {
+                    continue;
+                }
+                let multiples = Self::first_multiples(p, low);
+                if multiples.iter().all(|&byte| byte > last_byte) {
+                    continue;
+                }
+                if p >= segment_bytes {
+                    Self::add_to_buckets(
+                        &mut self.buckets, self.bucket, p, &multiples, last_byte,
+                        segment_bytes, isqrt(self.primes)
+                    )?;
+                    continue;
                 }
+                try_reserve(&mut self.primes_vec, 1)?;
+                try_reserve(&mut self.multiples, 1)?;
+                self.primes_vec.push(p);
+                self.multiples.push(multiples);
             }
-            i += 2
         }
 
-        // initialize sieving primes for segmented sieve
-        while s * s <= high
-        {
-            if self.is_prime[s] {
-                self.primes_vec.push(s);
-                self.multiples.push(s * s - low);
+        // sieve the current segment
+        Self::cross_off(
+            &self.primes_vec, &mut self.multiples, segment_bytes, sieve_segment
+        );
+        self.cross_off_buckets(last_byte, sieve_segment)?;
+
+        Ok(())
+    }
+
+    //Sets every candidate not a multiple of the presieve primes, except 1
+    //and those below the start of a range. Segments are whole words, each
+    //one copied from the pattern at the byte low falls on.
+    fn reset_segment(&self, low: usize, sieve_segment: &mut BitSlice) {
+        let pattern = presieve_pattern();
+        let mut byte = low / WHEEL_SIZE % PRESIEVE_BYTES;
+        let Domain::Region { head: None, body: words, tail: None } = sieve_segment.domain_mut() else {
+            unreachable!("segments are made of whole words");
+        };
+        for word in words {
+            *word = usize::from_le_bytes(pattern[byte..byte + WORD_BYTES].try_into().unwrap());
+            byte += WORD_BYTES;
+            if byte >= PRESIEVE_BYTES {
+                byte -= PRESIEVE_BYTES;
             }
-            s += 2
         }
+        if low == 0 {
+            for p in PRESIEVE_PRIMES {
+                sieve_segment.set(WHEEL_INDEX[p], true);
+            }
+        }
+        let first = max(self.start, 2);
+        if first > low {
+            let skip = min(wheel_count(first - 1 - low), sieve_segment.len());
+            sieve_segment[..skip].fill(false);
+        }
+    }
 
-        // sieve the current segment
-        for i in 0..self.primes_vec.len() {
-            let mut j: usize = self.multiples[i];
-            let k = self.primes_vec[i] * 2;
-            while j < self.segment_size {
-                if j % 2 == 1 {
-                    //replace unchecked is perfectly fine due to bounds [0, self.segment_size] being perfectly defined a priori.
-                    //j is always smaller than self.segment_size as per while condition.
+    fn cross_off(
+        primes_vec: &[usize], multiples: &mut [[usize; 8]], segment_bytes: usize,
+        sieve_segment: &mut BitSlice
+    ) {
+        for (p, multiple) in primes_vec.iter().zip(multiples.iter_mut()) {
+            for (r, byte) in WHEEL.iter().zip(multiple.iter_mut()) {
+                let bit = WHEEL_INDEX[p * r % WHEEL_SIZE];
+                let mut j: usize = *byte;
+                while j < segment_bytes {
+                    //replace unchecked is perfectly fine due to bounds [0, self.segment_bytes] being perfectly defined a priori.
+                    //j is always smaller than self.segment_bytes as per while condition.
                     unsafe {
-                        sieve_segment.replace_unchecked(j / 2, false)  
+                        sieve_segment.replace_unchecked(j * 8 + bit, false)
                     };
+                    j += p;
                 }
-                j += k;
+                *byte = j - segment_bytes;
             }
-            self.multiples[i] = j - self.segment_size;
         }
+    }
+
+    //Files each progression of p under the segment its first multiple
+    //falls in, as many segments ahead of the current one as its byte
+    //offset spans. None is further ahead than 1 + sqrt / segment_bytes.
+    fn add_to_buckets(
+        buckets: &mut Vec<Vec<Bucketed>>, bucket: usize, p: usize, multiples: &[usize; 8],
+        last_byte: usize, segment_bytes: usize, sqrt: usize
+    ) -> Result<(), SieveError> {
+        if buckets.is_empty() {
+            *buckets = try_vec(vec![], sqrt / segment_bytes + 2)?;
+        }
+        let len = buckets.len();
+        for (r, &byte) in WHEEL.iter().zip(multiples) {
+            if byte > last_byte {
+                continue;
+            }
+            let target = &mut buckets[(bucket + byte / segment_bytes) % len];
+            try_reserve(target, 1)?;
+            target.push(Bucketed {
+                prime: p,
+                bit: byte % segment_bytes * 8 + WHEEL_INDEX[p * r % WHEEL_SIZE],
+            });
+        }
+        Ok(())
+    }
+
+    //Clears the multiple of each progression in the current bucket and
+    //moves it on to the bucket of its next one, dropping those past the
+    //limit, then turns the ring to the next segment.
+    fn cross_off_buckets(
+        &mut self, last_byte: usize, sieve_segment: &mut BitSlice
+    ) -> Result<(), SieveError> {
+        if self.buckets.is_empty() {
+            return Ok(());
+        }
+        let segment_bits = self.segment_bytes() * 8;
+        let len = self.buckets.len();
+        //Every multiple moves at least a segment ahead, the current bucket
+        //only empties out and keeps its capacity for the turns to come.
+        let mut current = std::mem::take(&mut self.buckets[self.bucket]);
+        for Bucketed { prime, bit } in current.drain(..) {
+            //bit is always within the segment, buckets only hold multiples
+            //of the segment they belong to.
+            unsafe {
+                sieve_segment.replace_unchecked(bit, false)
+            };
+            let next = bit + prime * 8;
+            if next / 8 > last_byte {
+                continue;
+            }
+            let target = &mut self.buckets[(self.bucket + next / segment_bits) % len];
+            try_reserve(target, 1)?;
+            target.push(Bucketed { prime, bit: next % segment_bits });
+        }
+        self.buckets[self.bucket] = current;
+        self.bucket = (self.bucket + 1) % len;
+        Ok(())
+    }
 
-        let diff = (high - low - 1) / 2;
-        sieve_segment[..=diff].iter_ones().for_each(
+    fn calculate_primes_for_segment(
+        &mut self, low: usize, sieve_segment: &mut BitSlice
+    ) -> Result<(), SieveError> {
+        self.sieve_segment(low, sieve_segment)?;
+
+        let len = self.segment_len(low);
+        sieve_segment[..len].iter_ones().for_each(
             |i| {
-                self.primes_result.push(low + i * 2 + 1);
+                self.primes_result.push(T::from_usize(wheel_value(low, i)));
                 self.count += 1;
             }
         );
 
-        sieve_segment.fill(true);
+        Ok(())
+    }
+
+    fn sieve_segmented_loop(&mut self) -> Result<(), SieveError> {
+        let mut sieve_segment = self.new_segment()?;
+        try_reserve(&mut self.primes_result, pi_range_upper(self.start, self.primes))?;
 
-        (i, s)
+        let wheel_primes: Vec<usize> = self.wheel_primes().collect();
+        self.count = wheel_primes.len();
+        self.primes_result.extend(wheel_primes.into_iter().map(T::from_usize));
 
+        //segments hold numbers coprime to 30 so they must start on a multiple of it.
+        let mut low: usize = self.start - self.start % WHEEL_SIZE;
+    
+        while low < self.primes {
+            self.calculate_primes_for_segment(low, sieve_segment.as_mut_bitslice())?;
     
+            //Saturates once the segment reached usize::MAX.
+            low = low.saturating_add(self.segment_size);
+        }
+        Ok(())
     }
 
-    fn sieve_segmented_loop(&mut self) {
-        let vec = vec![usize::MAX; self.segment_size / (usize::BITS as usize)];
-        let mut sieve_segment = BitVec::from_vec(vec).into_boxed_bitslice();
-        assert_eq!(sieve_segment.len(), self.segment_size);
+    fn count_segmented_loop(&mut self) -> Result<(), SieveError> {
+        let mut count = self.wheel_primes().count();
+        let _ = self.for_each_segment(|_, sieve_segment| {
+            count += sieve_segment.count_ones();
+            ControlFlow::<()>::Continue(())
+        })?;
+        self.count = count;
+        Ok(())
+    }
 
-        self.primes_result.push(2);
-        self.count = 1;
+    //Hands every sieved segment to f along with its start, until f breaks.
+    fn for_each_segment<B, F>(&mut self, mut f: F) -> Result<ControlFlow<B>, SieveError>
+    where F: FnMut(usize, &BitSlice) -> ControlFlow<B> {
+        let mut sieve_segment = self.new_segment()?;
 
-        let mut i = 3;
-        let mut s = 3;
-    
-        let mut low: usize = 0;
+        let mut low: usize = self.start - self.start % WHEEL_SIZE;
 
-        //replace unchecked is perfectly fine due to bounds [0, self.segment_size] being perfectly defined a priori.
-        unsafe {
-            sieve_segment.replace_unchecked(0, false);
-        }
-    
-    
         while low < self.primes {
-            (i, s) = self.calculate_primes_for_segment(low, i, s, sieve_segment.as_mut_bitslice());
-    
-            low += self.segment_size;
+            self.sieve_segment(low, sieve_segment.as_mut_bitslice())?;
+            if let ControlFlow::Break(b) = f(low, &sieve_segment[..self.segment_len(low)]) {
+                return Ok(ControlFlow::Break(b));
+            }
+
+            low = low.saturating_add(self.segment_size);
+        }
+        Ok(ControlFlow::Continue(()))
+    }
+
+    //Hands every prime to f in order straight out of each segment,
+    //without storing them, until f breaks.
+    fn for_each_prime<B, F>(&mut self, mut f: F) -> Result<ControlFlow<B>, SieveError>
+    where F: FnMut(usize) -> ControlFlow<B> {
+        for p in self.wheel_primes().collect::<Vec<usize>>() {
+            if let ControlFlow::Break(b) = f(p) {
+                return Ok(ControlFlow::Break(b));
+            }
+        }
+        self.for_each_segment(|low, sieve_segment| {
+            for i in sieve_segment.iter_ones() {
+                f(wheel_value(low, i))?;
+            }
+            ControlFlow::Continue(())
+        })
+    }
+
+    //Every odd sieving prime up to sqrt at once, for workers that
+    //can't grow primes_vec as they go.
+    #[cfg(feature = "parallel")]
+    fn all_sieving_primes(&mut self) {
+        if let Some(sieving_primes) = self.sieving_primes.take() {
+            self.primes_vec.extend(sieving_primes.filter(|&p| p >= FIRST_SIEVING_PRIME));
+        }
+    }
+
+    //Sieves the segments in [low, high) with offsets of its own and
+    //hands each one to f along with its start.
+    #[cfg(feature = "parallel")]
+    fn sieve_chunk<F>(&self, mut low: usize, high: usize, mut f: F) -> Result<(), SieveError>
+    where F: FnMut(usize, &BitSlice) {
+        let mut sieve_segment = self.new_segment()?;
+        let mut multiples: Vec<[usize; 8]> = self.primes_vec.iter()
+            .map(|&p| Self::first_multiples(p, low))
+            .collect();
+
+        while low < high {
+            self.reset_segment(low, sieve_segment.as_mut_bitslice());
+            Self::cross_off(
+                &self.primes_vec, &mut multiples, self.segment_bytes(), sieve_segment.as_mut_bitslice()
+            );
+            f(low, &sieve_segment[..self.segment_len(low)]);
+
+            low = low.saturating_add(self.segment_size);
+        }
+        Ok(())
+    }
+
+    //Splits [0, primes] into runs of whole segments, a few per thread
+    //so uneven runs still balance out.
+    #[cfg(feature = "parallel")]
+    fn chunks(&self) -> Vec<(usize, usize)> {
+        let segments = self.primes.div_ceil(self.segment_size);
+        let per_chunk = max(1, segments / (rayon::current_num_threads() * 4));
+        let chunk_size = per_chunk * self.segment_size;
+        (0..segments.div_ceil(per_chunk))
+            .map(|c| (c * chunk_size, min((c + 1).saturating_mul(chunk_size), self.primes)))
+            .collect()
+    }
+
+    #[cfg(feature = "parallel")]
+    fn par_sieve_segmented_loop(&mut self) -> Result<(), SieveError> {
+        self.all_sieving_primes();
+
+        let chunks: Vec<Vec<T>> = self.chunks().into_par_iter()
+            .map(|(low, high)| {
+                let mut primes = vec![];
+                self.sieve_chunk(low, high, |low, sieve_segment| {
+                    primes.extend(sieve_segment.iter_ones().map(|i| T::from_usize(wheel_value(low, i))))
+                })?;
+                Ok(primes)
+            })
+            .collect::<Result<_, SieveError>>()?;
+
+        let total = chunks.iter().map(Vec::len).sum::<usize>() + WHEEL_PRIMES.len();
+        try_reserve(&mut self.primes_result, total)?;
+        self.primes_result.extend(self.wheel_primes().map(T::from_usize).collect::<Vec<T>>());
+        for chunk in chunks {
+            self.primes_result.extend(chunk);
+        }
+        self.count = self.primes_result.len();
+        Ok(())
+    }
+
+    #[cfg(feature = "parallel")]
+    fn par_count_segmented_loop(&mut self) -> Result<(), SieveError> {
+        self.all_sieving_primes();
+
+        self.count = self.wheel_primes().count() + self.chunks().into_par_iter()
+            .map(|(low, high)| {
+                let mut count = 0;
+                self.sieve_chunk(low, high, |_, sieve_segment| {
+                    count += sieve_segment.count_ones()
+                })?;
+                Ok(count)
+            })
+            .sum::<Result<usize, SieveError>>()?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Bound {
+    Limit(usize),
+    First(usize),
+}
+
+//Configures and runs a SieveSegmented, primes are yielded as T and the
+//sink is picked by whichever of collect, count or for_each runs it.
+#[derive(Debug, Clone, Copy)]
+pub struct SieveSegmentedBuilder<T = usize> {
+    bound: Bound,
+    start: usize,
+    config: SieveConfig,
+    width: PhantomData<T>,
+}
+
+impl<T> SieveSegmentedBuilder<T> {
+    //Sieve every prime up to primes.
+    pub fn limit(self, primes: usize) -> SieveSegmentedBuilder<T> {
+        SieveSegmentedBuilder { bound: Bound::Limit(primes), ..self }
+    }
+
+    //Sieve the first n primes.
+    pub fn first(self, n: usize) -> SieveSegmentedBuilder<T> {
+        SieveSegmentedBuilder { bound: Bound::First(n), ..self }
+    }
+
+    //Skip every prime below start.
+    pub fn start(self, start: usize) -> SieveSegmentedBuilder<T> {
+        SieveSegmentedBuilder { start, ..self }
+    }
+
+    pub fn config(self, config: SieveConfig) -> SieveSegmentedBuilder<T> {
+        SieveSegmentedBuilder { config, ..self }
+    }
+
+    pub fn segment_bytes(self, segment_bytes: usize) -> SieveSegmentedBuilder<T> {
+        let config = self.config.with_segment_bytes(segment_bytes);
+        self.config(config)
+    }
+
+    //Integer type primes are output as.
+    pub fn width<U>(self) -> SieveSegmentedBuilder<U> {
+        SieveSegmentedBuilder {
+            bound: self.bound,
+            start: self.start,
+            config: self.config,
+            width: PhantomData,
+        }
+    }
+
+    //Primes in [lo, hi] in order, until f breaks.
+    fn window<B, F>(
+        lo: usize, hi: usize, config: SieveConfig, f: &mut F
+    ) -> Result<ControlFlow<B>, SieveError>
+    where F: FnMut(usize) -> ControlFlow<B> {
+        if lo > hi || hi < 2 {
+            return Ok(ControlFlow::Continue(()));
+        }
+        let mut sieve_segmented = SieveSegmented::<usize>::new_range(lo, hi, config)?;
+        sieve_segmented.for_each_prime(f)
+    }
+
+    fn run<B, F>(&self, mut f: F) -> Result<ControlFlow<B>, SieveError>
+    where F: FnMut(usize) -> ControlFlow<B> {
+        match self.bound {
+            Bound::Limit(primes) => {
+                SieveSegmentedBuilder::<T>::window(self.start, primes, self.config, &mut f)
+            }
+            Bound::First(0) => Ok(ControlFlow::Continue(())),
+            Bound::First(n) => {
+                let config = self.config;
+                SieveSegmentedBuilder::<T>::first_windows(self.start, n, |lo, hi, left| {
+                    let mut taken = 0;
+                    let flow = SieveSegmentedBuilder::<T>::window(lo, hi, config, &mut |p| {
+                        if let ControlFlow::Break(b) = f(p) {
+                            return ControlFlow::Break(Some(b));
+                        }
+                        taken += 1;
+                        if taken == left {
+                            return ControlFlow::Break(None);
+                        }
+                        ControlFlow::Continue(())
+                    })?;
+                    match flow {
+                        ControlFlow::Break(Some(b)) => Ok(ControlFlow::Break(b)),
+                        _ => Ok(ControlFlow::Continue(taken)),
+                    }
+                })
+            }
+        }
+    }
+
+    //Windows [lo, hi] from start until the first n primes were taken, window
+    //takes at most left of the primes in [lo, hi] and returns how many. By
+    //the bounds the first window already holds n primes, windows only grow
+    //if it saturated at usize::MAX.
+    fn first_windows<B, W>(
+        start: usize, n: usize, mut window: W
+    ) -> Result<ControlFlow<B>, SieveError>
+    where W: FnMut(usize, usize, usize) -> Result<ControlFlow<B, usize>, SieveError> {
+        let mut left = n;
+        let mut lo = start;
+        let mut width = nth_prime_upper(pi_upper(lo).saturating_add(n)).saturating_sub(lo);
+        loop {
+            let hi = lo.saturating_add(width);
+            match window(lo, hi, left)? {
+                ControlFlow::Break(b) => return Ok(ControlFlow::Break(b)),
+                ControlFlow::Continue(taken) if taken == left => {
+                    return Ok(ControlFlow::Continue(()));
+                }
+                ControlFlow::Continue(_) if hi == usize::MAX => {
+                    return Err(SieveError::Overflow { what: "prime number", value: n as u64 });
+                }
+                ControlFlow::Continue(taken) => left -= taken,
+            }
+            lo = hi + 1;
+            width = width.saturating_mul(2);
+        }
+    }
+
+    //Amount of primes, limits are counted a segment at a time without
+    //looking at single primes.
+    pub fn count(self) -> u64 {
+        self.try_count().or_panic()
+    }
+
+    pub fn try_count(self) -> Result<u64, SieveError> {
+        match self.bound {
+            Bound::Limit(primes) if primes >= self.start => {
+                let mut sieve_segmented =
+                    SieveSegmented::<usize>::new_range(self.start, primes, self.config)?;
+                sieve_segmented.count_segmented_loop()?;
+                Ok(sieve_segmented.count as u64)
+            }
+            Bound::Limit(_) => Ok(0),
+            Bound::First(_) => {
+                let mut count = 0;
+                let _ = self.run(|_| {
+                    count += 1;
+                    ControlFlow::<()>::Continue(())
+                })?;
+                Ok(count)
+            }
+        }
+    }
+}
+
+impl<T: PrimeInt> SieveSegmentedBuilder<T> {
+    fn check_width(&self) -> Result<(), SieveError> {
+        match self.bound {
+            Bound::Limit(primes) => check_width::<T>("limit", primes),
+            Bound::First(_) => Ok(()),
+        }
+    }
+
+    pub fn collect(self) -> Vec<T> {
+        self.try_collect().or_panic()
+    }
+
+    pub fn try_collect(self) -> Result<Vec<T>, SieveError> {
+        let mut primes = vec![];
+        self.try_for_each(|p| primes.push(p))?;
+        Ok(primes)
+    }
+
+    pub fn for_each<F>(self, f: F)
+    where F: FnMut(T) {
+        self.try_for_each(f).or_panic()
+    }
+
+    //Primes past the output width stop the run with an error.
+    pub fn try_for_each<F>(self, mut f: F) -> Result<(), SieveError>
+    where F: FnMut(T) {
+        self.check_width()?;
+        let flow = self.run(|p| {
+            if !T::fits(p) {
+                return ControlFlow::Break(p);
+            }
+            f(T::from_usize(p));
+            ControlFlow::Continue(())
+        })?;
+        match flow {
+            ControlFlow::Break(p) => Err(SieveError::Overflow { what: "prime", value: p as u64 }),
+            ControlFlow::Continue(()) => Ok(()),
+        }
+    }
+}
+
+//Lazily yields the primes up to a limit, sieving a single segment
+//at a time so only the segment and the sieving primes are kept in memory.
+#[derive(Debug)]
+pub struct PrimeIter {
+    sieve: SieveSegmented,
+    sieve_segment: BitBox,
+    //Start of the segment currently held in sieve_segment.
+    low: usize,
+    //Start of the next segment to sieve.
+    next_low: usize,
+    //Next bit to inspect and number of valid bits in sieve_segment.
+    pos: usize,
+    len: usize,
+    wheel_primes: std::vec::IntoIter<usize>,
+}
+
+impl PrimeIter {
+    fn new(primes: usize) -> Result<PrimeIter, SieveError> {
+        let sieve = SieveSegmented::<usize>::new(primes, SieveConfig::default())?;
+        let sieve_segment = sieve.new_segment()?;
+        let wheel_primes: Vec<usize> = sieve.wheel_primes().collect();
+        Ok(PrimeIter {
+            sieve,
+            sieve_segment,
+            low: 0,
+            next_low: 0,
+            pos: 0,
+            len: 0,
+            wheel_primes: wheel_primes.into_iter(),
+        })
+    }
+
+    fn next_segment(&mut self) -> bool {
+        if self.next_low >= self.sieve.primes {
+            return false;
+        }
+        self.low = self.next_low;
+        //Iterators can't fail, running out of memory for sieving primes panics.
+        self.sieve.sieve_segment(self.low, self.sieve_segment.as_mut_bitslice()).or_panic();
+        self.pos = 0;
+        self.len = self.sieve.segment_len(self.low);
+        self.next_low = self.next_low.saturating_add(self.sieve.segment_size);
+        true
+    }
+}
+
+impl Iterator for PrimeIter {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if let Some(p) = self.wheel_primes.next() {
+            return Some(p);
+        }
+        loop {
+            if let Some(i) = self.sieve_segment[self.pos..self.len].first_one() {
+                let i = self.pos + i;
+                self.pos = i + 1;
+                return Some(wheel_value(self.low, i));
+            }
+            if !self.next_segment() {
+                return None;
+            }
         }
-            
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        //Primes left in the current segment are already known exactly,
+        //everything past it is bounded by the remaining candidates.
+        let known = self.sieve_segment[self.pos..self.len].count_ones() + self.wheel_primes.len();
+        let unsieved = if self.next_low < self.sieve.primes {
+            wheel_count(self.sieve.primes) - self.next_low / WHEEL_SIZE * 8
+        } else {
+            0
+        };
+        (known, Some(known + unsieved))
     }
 }
 
+pub fn prime_iter(primes: usize) -> PrimeIter {
+    try_prime_iter(primes).or_panic()
+}
+
+pub fn try_prime_iter(primes: usize) -> Result<PrimeIter, SieveError> {
+    PrimeIter::new(primes)
+}
+
 #[wasm_bindgen]
-pub fn sieve_segmented_w(primes: usize) -> JsValue {
-    let sieve_segmented = sieve_segmented(primes);
+pub fn sieve_segmented_w(primes: usize) -> Result<JsValue, JsError> {
+    let sieve_segmented = try_sieve_segmented::<usize>(primes)?;
+
+    Ok(to_js(&sieve_segmented.primes_result)?)
+}
 
-    JsValue::from_serde(&sieve_segmented.primes_result).unwrap()
+pub fn sieve_segmented<T: PrimeInt>(primes: usize) -> SieveSegmented<T> {
+    try_sieve_segmented(primes).or_panic()
 }
 
-pub fn sieve_segmented(primes: usize) -> SieveSegmented {
+pub fn try_sieve_segmented<T: PrimeInt>(primes: usize) -> Result<SieveSegmented<T>, SieveError> {
+    try_sieve_segmented_with(primes, SieveConfig::default())
+}
+
+pub fn sieve_segmented_with<T: PrimeInt>(primes: usize, config: SieveConfig) -> SieveSegmented<T> {
+    try_sieve_segmented_with(primes, config).or_panic()
+}
+
+pub fn try_sieve_segmented_with<T: PrimeInt>(
+    primes: usize, config: SieveConfig
+) -> Result<SieveSegmented<T>, SieveError> {
     match primes {
-        0 | 1 => SieveSegmented::empty(),
-        2 => SieveSegmented::single(),
+        0 | 1 => Ok(SieveSegmented::empty()),
+        2 => Ok(SieveSegmented::single()),
         _ => {
-            let mut sieve_segmented = SieveSegmented::new(primes);
-            sieve_segmented.sieve_segmented_loop();
+            let mut sieve_segmented = SieveSegmented::new(primes, config)?;
+            sieve_segmented.sieve_segmented_loop()?;
             //Circumvents non-primes or primes within segment bigger than primes.
             /* 
             let pop_cnt = {
@@ -203,32 +908,165 @@ pub fn sieve_segmented(primes: usize) -> SieveSegmented {
                 }    
             }
             */
-            sieve_segmented
+            Ok(sieve_segmented)
         }
     }
 }
 
 
 #[wasm_bindgen]
-pub fn n_primes_segmented_w(primes: usize) -> JsValue {
-    let sieve_segmented = n_primes_segmented(primes);
+pub fn count_primes_w(primes: usize) -> Result<u64, JsError> {
+    Ok(try_count_primes(primes)?)
+}
+
+//pi(primes), each segment is counted with a popcount and no prime is stored.
+pub fn count_primes(primes: usize) -> u64 {
+    try_count_primes(primes).or_panic()
+}
+
+pub fn try_count_primes(primes: usize) -> Result<u64, SieveError> {
+    try_count_primes_with(primes, SieveConfig::default())
+}
 
-    JsValue::from_serde(&sieve_segmented.primes_result).unwrap()
+pub fn count_primes_with(primes: usize, config: SieveConfig) -> u64 {
+    try_count_primes_with(primes, config).or_panic()
 }
 
-pub fn n_primes_segmented(primes: usize) -> SieveSegmented {
+pub fn try_count_primes_with(primes: usize, config: SieveConfig) -> Result<u64, SieveError> {
     match primes {
-        0 => SieveSegmented::empty(),
-        1 => SieveSegmented::single(),
+        0 | 1 => Ok(0),
+        2 => Ok(1),
         _ => {
-            let mut sieve_segmented = SieveSegmented::new_n(primes);
-            sieve_segmented.sieve_segmented_loop();
-    
-            sieve_segmented.primes_result.drain(primes..);
-    
-            sieve_segmented
+            let mut sieve_segmented = SieveSegmented::<usize>::new(primes, config)?;
+            sieve_segmented.count_segmented_loop()?;
+            Ok(sieve_segmented.count as u64)
         }
+    }
+}
+
+
+//Same output as sieve_segmented, with segments spread over the rayon pool.
+#[cfg(feature = "parallel")]
+pub fn par_sieve_segmented<T: PrimeInt>(primes: usize) -> SieveSegmented<T> {
+    try_par_sieve_segmented(primes).or_panic()
+}
 
+#[cfg(feature = "parallel")]
+pub fn try_par_sieve_segmented<T: PrimeInt>(
+    primes: usize
+) -> Result<SieveSegmented<T>, SieveError> {
+    match primes {
+        0 | 1 => Ok(SieveSegmented::empty()),
+        2 => Ok(SieveSegmented::single()),
+        _ => {
+            let mut sieve_segmented = SieveSegmented::new(primes, SieveConfig::default())?;
+            sieve_segmented.par_sieve_segmented_loop()?;
+            Ok(sieve_segmented)
+        }
+    }
+}
+
+//Same output as count_primes, with segments spread over the rayon pool.
+#[cfg(feature = "parallel")]
+pub fn par_count_primes(primes: usize) -> u64 {
+    try_par_count_primes(primes).or_panic()
+}
+
+#[cfg(feature = "parallel")]
+pub fn try_par_count_primes(primes: usize) -> Result<u64, SieveError> {
+    match primes {
+        0 | 1 => Ok(0),
+        2 => Ok(1),
+        _ => {
+            let mut sieve_segmented = SieveSegmented::<usize>::new(primes, SieveConfig::default())?;
+            sieve_segmented.par_count_segmented_loop()?;
+            Ok(sieve_segmented.count as u64)
+        }
+    }
+}
+
+
+//Folds over the primes up to primes without collecting them.
+pub fn for_each_prime<F>(primes: usize, f: F)
+where F: FnMut(usize) {
+    try_for_each_prime(primes, f).or_panic()
+}
+
+pub fn try_for_each_prime<F>(primes: usize, mut f: F) -> Result<(), SieveError>
+where F: FnMut(usize) {
+    let _ = try_for_each_prime_until(primes, |p| {
+        f(p);
+        ControlFlow::<()>::Continue(())
+    })?;
+    Ok(())
+}
+
+//As for_each_prime, stopping at the first prime f breaks on.
+pub fn for_each_prime_until<B, F>(primes: usize, f: F) -> ControlFlow<B>
+where F: FnMut(usize) -> ControlFlow<B> {
+    try_for_each_prime_until(primes, f).or_panic()
+}
+
+pub fn try_for_each_prime_until<B, F>(primes: usize, f: F) -> Result<ControlFlow<B>, SieveError>
+where F: FnMut(usize) -> ControlFlow<B> {
+    match primes {
+        0 | 1 => Ok(ControlFlow::Continue(())),
+        _ => {
+            let mut sieve_segmented = SieveSegmented::<usize>::new(primes, SieveConfig::default())?;
+            sieve_segmented.for_each_prime(f)
+        }
+    }
+}
+
+
+#[wasm_bindgen]
+pub fn sieve_range_w(lo: usize, hi: usize) -> Result<JsValue, JsError> {
+    let sieve_segmented = try_sieve_range::<usize>(lo, hi)?;
+
+    Ok(to_js(&sieve_segmented.primes_result)?)
+}
+
+//Primes in [lo, hi], only sieving primes up to isqrt(hi) are generated
+//and segments start at lo instead of 0.
+pub fn sieve_range<T: PrimeInt>(lo: usize, hi: usize) -> SieveSegmented<T> {
+    try_sieve_range(lo, hi).or_panic()
+}
+
+pub fn try_sieve_range<T: PrimeInt>(lo: usize, hi: usize) -> Result<SieveSegmented<T>, SieveError> {
+    match hi {
+        _ if lo > hi => Ok(SieveSegmented::empty()),
+        0 | 1 => Ok(SieveSegmented::empty()),
+        2 => Ok(SieveSegmented::single()),
+        _ => {
+            let mut sieve_segmented = SieveSegmented::new_range(lo, hi, SieveConfig::default())?;
+            sieve_segmented.sieve_segmented_loop()?;
+            Ok(sieve_segmented)
+        }
+    }
+}
+
+
+#[wasm_bindgen]
+pub fn n_primes_segmented_w(primes: usize) -> Result<JsValue, JsError> {
+    let sieve_segmented = try_n_primes_segmented::<usize>(primes)?;
+
+    Ok(to_js(&sieve_segmented.primes_result)?)
+}
+
+pub fn n_primes_segmented<T: PrimeInt>(primes: usize) -> SieveSegmented<T> {
+    try_n_primes_segmented(primes).or_panic()
+}
+
+pub fn try_n_primes_segmented<T: PrimeInt>(primes: usize) -> Result<SieveSegmented<T>, SieveError> {
+    match primes {
+        0 => Ok(SieveSegmented::empty()),
+        1 => Ok(SieveSegmented::single()),
+        _ => {
+            let mut sieve_segmented = SieveSegmented::new_n(primes, SieveConfig::default())?;
+            sieve_segmented.sieve_segmented_loop()?;
+            sieve_segmented.primes_result = first_n(sieve_segmented.primes_result, primes)?;
+            Ok(sieve_segmented)
+        }
     }
 }
 
@@ -264,4 +1102,323 @@ mod tests {
         assert_n(3, &primes, |n| n_primes_segmented(n).primes_result);
         assert_n(303, &primes, |n| n_primes_segmented(n).primes_result);
     }
+
+    #[test]
+    fn segmented_iter_by_limit() {
+        use super::prime_iter;
+
+        let primes_test = [0, 1, 2, 5, 2000, 1048577, 3000000];
+        let primes: Sieve = Sieve::new(*primes_test.iter().max().unwrap());
+        for i in primes_test {
+            assert_primes(i, &primes, |n| prime_iter(n).collect());
+        }
+    }
+
+    #[test]
+    fn segmented_iter_size_hint() {
+        use super::prime_iter;
+
+        let mut iter = prime_iter(1500000);
+        let mut remaining = prime_iter(1500000).count();
+        loop {
+            let (lower, upper) = iter.size_hint();
+            assert!(lower <= remaining);
+            assert!(remaining <= upper.unwrap());
+            if iter.nth(9999).is_none() {
+                break;
+            }
+            remaining -= 10000;
+        }
+    }
+
+    #[test]
+    fn segmented_range() {
+        use super::sieve_range;
+
+        let ranges = [(0, 0), (3, 2), (2, 2), (3, 3), (4, 4), (0, 2000), (1000, 2000), (999, 1999),
+            (524287, 524289), (983039, 983071), (1000000, 3000000), (2999999, 3000017)];
+        let primes: Sieve = Sieve::new(3000017);
+        for (lo, hi) in ranges {
+            let expected: Vec<usize> = primes.primes_from(lo).take_while(|&n| n <= hi).collect();
+            assert_eq!(sieve_range::<usize>(lo, hi).primes_result, expected);
+        }
+    }
+
+    #[test]
+    fn segmented_range_wheel_offsets() {
+        use super::sieve_range;
+
+        let primes: Sieve = Sieve::new(1000);
+        for lo in 0..=60 {
+            for hi in [lo, lo + 1, lo + 29, lo + 30, lo + 31, 1000] {
+                let expected: Vec<usize> = primes.primes_from(lo).take_while(|&n| n <= hi).collect();
+                assert_eq!(sieve_range::<usize>(lo, hi).primes_result, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn segmented_presieve() {
+        use super::{SieveConfig, SieveSegmented, PRESIEVE_BYTES, WHEEL_SIZE};
+
+        //Word-sized segments wrap around the pattern at every byte offset.
+        let period = PRESIEVE_BYTES * WHEEL_SIZE;
+        let primes: Sieve = Sieve::new(2 * period + 1000);
+        let builder = SieveSegmented::builder().config(SieveConfig::detect().with_segment_bytes(8));
+        for lo in (0..=8 * WHEEL_SIZE).map(|i| period - 4 * WHEEL_SIZE + i) {
+            let hi = lo + 1000;
+            let expected: Vec<usize> = primes.primes_from(lo).take_while(|&n| n <= hi).collect();
+            assert_eq!(builder.start(lo).limit(hi).collect(), expected);
+        }
+        for limit in [6, 7, 17, 18, 19, 2 * period + 1000] {
+            let expected = primes.prime_pi(limit) as u64;
+            assert_eq!(builder.limit(limit).count(), expected);
+        }
+    }
+
+    #[test]
+    fn segmented_buckets() {
+        use super::{count_primes_with, SieveConfig, SieveSegmented};
+        use crate::primality::is_prime_u64;
+
+        //Segments as narrow as the square root of the limit, so most
+        //sieving primes skip whole segments and many never reach the limit.
+        let config = SieveConfig::detect().with_segment_bytes(8);
+        assert_eq!(count_primes_with(100000000, config), 5761455);
+        let builder = SieveSegmented::builder().config(config);
+        for lo in [10000000000, 10000000000 - 29, 999999999999989] {
+            let hi = lo + 300000;
+            let expected: Vec<usize> = (lo..=hi).filter(|&n| is_prime_u64(n as u64)).collect();
+            assert_eq!(builder.start(lo).limit(hi).collect(), expected);
+        }
+    }
+
+    #[test]
+    fn segmented_range_far() {
+        use super::sieve_range;
+        use primal_sieve::Primes;
+
+        let lo = 1_000_000_000_000;
+        let hi = lo + 100_000;
+        let result = sieve_range::<usize>(lo, hi).primes_result;
+        assert!(result.iter().all(|&p| (lo..=hi).contains(&p)));
+        assert_eq!(result.len(), 3614);
+        let small: Vec<usize> = Primes::all().take_while(|&p| p * p <= hi).collect();
+        for n in lo..=hi {
+            let composite = small.iter().any(|&p| n % p == 0);
+            assert_eq!(result.binary_search(&n).is_ok(), !composite);
+        }
+    }
+
+    #[test]
+    fn segmented_count() {
+        use super::count_primes;
+
+        let primes_test = [0, 1, 2, 3, 4, 5, 2000, 524288, 524289, 16777216];
+        let primes: Sieve = Sieve::new(*primes_test.iter().max().unwrap());
+        for i in primes_test {
+            assert_eq!(count_primes(i), primes.prime_pi(i) as u64);
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn segmented_parallel() {
+        use super::{count_primes, par_count_primes, par_sieve_segmented, sieve_segmented};
+
+        for i in [0, 1, 2, 3, 5, 2000, 524288, 524289, 1048577, 16777216] {
+            assert_eq!(par_sieve_segmented::<usize>(i).primes_result, sieve_segmented::<usize>(i).primes_result);
+            assert_eq!(par_count_primes(i), count_primes(i));
+        }
+    }
+
+    #[test]
+    fn segmented_config() {
+        use super::{count_primes_with, sieve_segmented, sieve_segmented_with, SieveConfig};
+
+        let expected = sieve_segmented::<usize>(3000000).primes_result;
+        for bytes in [0, 1, 64, 1000, 4096, 32768, 1 << 20] {
+            let config = SieveConfig::default().with_segment_bytes(bytes);
+            assert!(config.segment_bytes() >= bytes);
+            assert_eq!(config.segment_bytes() % size_of::<usize>(), 0);
+
+            let sieve = sieve_segmented_with::<usize>(3000000, config);
+            assert!(sieve.segment_bytes() >= config.segment_bytes().min(3000001 / 30));
+            assert_eq!(sieve.primes_result, expected);
+            assert_eq!(count_primes_with(3000000, config), expected.len() as u64);
+        }
+    }
+
+    #[test]
+    fn segmented_builder() {
+        use super::{n_primes_segmented, sieve_range, sieve_segmented, SieveSegmented};
+
+        let expected = sieve_segmented::<usize>(3000000).primes_result;
+        let builder = SieveSegmented::builder().limit(3000000);
+        assert_eq!(builder.collect(), expected);
+        assert_eq!(builder.count(), expected.len() as u64);
+        assert_eq!(builder.segment_bytes(64).collect(), expected);
+
+        let mut sum = 0;
+        builder.for_each(|p| sum += p);
+        assert_eq!(sum, expected.iter().sum::<usize>());
+
+        let narrow: Vec<u32> = builder.width::<u32>().collect();
+        assert!(narrow.iter().map(|&p| p as usize).eq(expected.iter().copied()));
+
+        let builder = SieveSegmented::builder().start(1000000);
+        assert_eq!(builder.limit(3000000).collect(), sieve_range::<usize>(1000000, 3000000).primes_result);
+        assert_eq!(builder.limit(3000000).count(), sieve_range::<usize>(1000000, 3000000).primes_result.len() as u64);
+        assert_eq!(builder.limit(999999).count(), 0);
+
+        for n in [0, 1, 2, 3, 4, 303, 100000] {
+            let builder = SieveSegmented::builder().first(n);
+            assert_eq!(builder.collect(), n_primes_segmented::<usize>(n).primes_result);
+            assert_eq!(builder.count(), n as u64);
+        }
+
+        let after: Vec<usize> = SieveSegmented::builder().start(1_000_000_000_000).first(20).collect();
+        assert_eq!(after, sieve_range::<usize>(1_000_000_000_000, after[19]).primes_result);
+        assert_eq!(after.len(), 20);
+    }
+
+    #[test]
+    #[should_panic(expected = "overflows the output width")]
+    fn segmented_builder_overflow() {
+        use super::SieveSegmented;
+
+        SieveSegmented::builder().width::<u32>().limit(u32::MAX as usize + 1).collect();
+    }
+
+    #[test]
+    fn segmented_narrow() {
+        use super::{n_primes_segmented, sieve_segmented};
+
+        let wide: Vec<usize> = sieve_segmented(3000000).primes_result;
+        let narrow: Vec<u32> = sieve_segmented(3000000).primes_result;
+        assert!(narrow.iter().map(|&p| p as usize).eq(wide));
+
+        let wide: Vec<usize> = n_primes_segmented(2000).primes_result;
+        let narrow: Vec<u32> = n_primes_segmented(2000).primes_result;
+        assert!(narrow.iter().map(|&p| p as usize).eq(wide));
+    }
+
+    #[test]
+    #[should_panic(expected = "overflows the output width")]
+    fn segmented_narrow_overflow() {
+        use super::sieve_segmented;
+
+        sieve_segmented::<u32>(u32::MAX as usize + 1);
+    }
+
+    #[test]
+    fn segmented_for_each() {
+        use std::ops::ControlFlow;
+        use super::{for_each_prime, for_each_prime_until, sieve_segmented};
+
+        for i in [0, 1, 2, 3, 5, 2000, 3000000] {
+            let expected: Vec<usize> = sieve_segmented(i).primes_result;
+            let mut primes = vec![];
+            for_each_prime(i, |p| primes.push(p));
+            assert_eq!(primes, expected);
+        }
+
+        let mut seen = 0;
+        let mut past_million = |p| {
+            seen += 1;
+            if p > 1000000 { ControlFlow::Break(p) } else { ControlFlow::Continue(()) }
+        };
+        assert_eq!(for_each_prime_until(1000000, &mut past_million), ControlFlow::Continue(()));
+        assert_eq!(for_each_prime_until(3000000, &mut past_million), ControlFlow::Break(1000003));
+        assert_eq!(seen, 78498 + 78499);
+    }
+
+    #[test]
+    fn segmented_errors() {
+        use super::{try_count_primes, try_n_primes_segmented, try_sieve_segmented, SieveSegmented};
+        use crate::error::SieveError;
+
+        //Every prime below 2^64 is too many to hold.
+        assert!(matches!(
+            try_sieve_segmented::<usize>(usize::MAX),
+            Err(SieveError::AllocationFailure { .. })
+        ));
+        assert_eq!(try_count_primes(2000), Ok(303));
+        assert_eq!(try_n_primes_segmented::<u32>(303).unwrap().primes_result.len(), 303);
+
+        //The last primes below 2^32 fit, the next one doesn't.
+        let builder = SieveSegmented::builder().width::<u32>().start(u32::MAX as usize - 1000);
+        assert_eq!(builder.limit(u32::MAX as usize).try_collect().unwrap().len(), 36);
+        assert_eq!(
+            builder.first(37).try_collect(),
+            Err(SieveError::Overflow { what: "prime", value: 4294967311 })
+        );
+    }
+
+    #[test]
+    fn segmented_top_helpers() {
+        use std::cmp::{max, min};
+        use std::ops::ControlFlow;
+        use super::{SieveConfig, SieveSegmented, SieveSegmentedBuilder, WHEEL, WORD_SPAN};
+        use crate::error::SieveError;
+        use crate::primality::is_prime_u64;
+
+        //Against the multiples themselves, which only fit in a u128.
+        let p = 4294967291;
+        for low in [usize::MAX - 15, usize::MAX - 30015, p * p / 30 * 30] {
+            let q = max(low.div_ceil(p), p);
+            let offsets = SieveSegmented::<usize>::first_multiples(p, low);
+            for (&r, offset) in WHEEL.iter().zip(offsets) {
+                let q = (q..).find(|q| q % 30 == r).unwrap();
+                assert_eq!(offset as u128, (p as u128 * q as u128 - low as u128) / 30);
+            }
+        }
+
+        let config = SieveConfig::default();
+        let fit = SieveSegmented::<usize>::fit_segment_size;
+        assert_eq!(fit(usize::MAX - 100, usize::MAX, config), WORD_SPAN);
+        let huge = config.with_segment_bytes(usize::MAX);
+        assert_eq!(fit(0, usize::MAX, huge), usize::MAX / WORD_SPAN * WORD_SPAN);
+
+        //Sieving primes are only taken once sieving starts.
+        let new_range = SieveSegmented::<usize>::new_range;
+        let top = new_range(usize::MAX - 100, usize::MAX, config).unwrap();
+        assert_eq!(top.segment_high(usize::MAX - 15), usize::MAX);
+        let below = new_range(usize::MAX - 100, usize::MAX - 20, config).unwrap();
+        assert_eq!(below.segment_high(usize::MAX - 105), usize::MAX - 20);
+
+        //Windows up to usize::MAX holding fewer than n primes.
+        let mut windows = vec![];
+        let start = usize::MAX - 100;
+        let flow = SieveSegmentedBuilder::<usize>::first_windows(start, 10, |lo, hi, left| {
+            windows.push((lo, hi));
+            let primes = (lo..=hi).filter(|&n| is_prime_u64(n as u64)).count();
+            Ok(ControlFlow::<(), _>::Continue(min(primes, left)))
+        });
+        assert_eq!(flow, Err(SieveError::Overflow { what: "prime number", value: 10 }));
+        assert_eq!(windows.first().map(|w| w.0), Some(usize::MAX - 100));
+        assert_eq!(windows.last().map(|w| w.1), Some(usize::MAX));
+    }
+
+    #[test]
+    #[ignore = "sieves every prime below 2^32, run with --release -- --ignored"]
+    fn segmented_top_of_range() {
+        use super::{try_sieve_range, SieveSegmented};
+        use crate::error::SieveError;
+        use crate::primality::{is_prime_u64, prev_prime};
+
+        //The sieving primes run up to 2^32 and their multiples up to 2^64.
+        let lo = usize::MAX - 100000;
+        let result = try_sieve_range::<usize>(lo, usize::MAX).unwrap().primes_result;
+        let expected: Vec<usize> = (lo..=usize::MAX).filter(|&n| is_prime_u64(n as u64)).collect();
+        assert_eq!(result, expected);
+        assert_eq!(result.last().map(|&p| p as u64), prev_prime(u64::MAX));
+
+        let builder = SieveSegmented::builder().start(lo);
+        assert_eq!(builder.limit(usize::MAX).count(), expected.len() as u64);
+        assert_eq!(
+            builder.first(expected.len() + 1).try_collect(),
+            Err(SieveError::Overflow { what: "prime number", value: expected.len() as u64 + 1 })
+        );
+    }
 }
@@ -0,0 +1,149 @@
+use crate::atkin::{try_n_primes_atkin, try_sieve_atkin_with};
+use crate::dynamic_wheel::{dynamic_wheel, try_n_primes_dynamic_wheel, try_sieve_dynamic_wheel};
+use crate::error::{OrPanic, SieveError};
+use crate::linear::{try_n_primes_linear, try_sieve_linear};
+use crate::pritchard::{try_n_primes_pritchard, try_sieve_pritchard};
+use crate::segmented::{
+    try_count_primes_with, try_n_primes_segmented, try_prime_iter, try_sieve_segmented_with,
+    SieveConfig,
+};
+use crate::utils::PrimeInt;
+
+//Common interface over every algorithm, so harnesses can be written once
+//and run against any of them. Only try_primes_up_to and try_first_n_primes
+//are required, counting and iterating fall back to collecting the primes.
+//The methods without try_ panic on the errors of their try_ counterparts.
+pub trait PrimeSieve {
+    //Every prime p <= limit, in increasing order.
+    fn try_primes_up_to<T: PrimeInt>(&self, limit: usize) -> Result<Vec<T>, SieveError>;
+
+    //The first n primes.
+    fn try_first_n_primes<T: PrimeInt>(&self, n: usize) -> Result<Vec<T>, SieveError>;
+
+    //pi(limit).
+    fn try_count_up_to(&self, limit: usize) -> Result<u64, SieveError> {
+        Ok(self.try_primes_up_to::<usize>(limit)?.len() as u64)
+    }
+
+    //Primes up to limit, lazily where the algorithm allows it.
+    fn try_iter(&self, limit: usize) -> Result<Box<dyn Iterator<Item = usize>>, SieveError> {
+        Ok(Box::new(self.try_primes_up_to::<usize>(limit)?.into_iter()))
+    }
+
+    fn primes_up_to<T: PrimeInt>(&self, limit: usize) -> Vec<T> {
+        self.try_primes_up_to(limit).or_panic()
+    }
+
+    fn first_n_primes<T: PrimeInt>(&self, n: usize) -> Vec<T> {
+        self.try_first_n_primes(n).or_panic()
+    }
+
+    fn count_up_to(&self, limit: usize) -> u64 {
+        self.try_count_up_to(limit).or_panic()
+    }
+
+    fn iter(&self, limit: usize) -> Box<dyn Iterator<Item = usize>> {
+        self.try_iter(limit).or_panic()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PritchardSieve;
+
+impl PrimeSieve for PritchardSieve {
+    fn try_primes_up_to<T: PrimeInt>(&self, limit: usize) -> Result<Vec<T>, SieveError> {
+        try_sieve_pritchard(limit)
+    }
+
+    fn try_first_n_primes<T: PrimeInt>(&self, n: usize) -> Result<Vec<T>, SieveError> {
+        try_n_primes_pritchard(n)
+    }
+}
+
+//config sizes the segments of primes_up_to and count_up_to, first_n_primes
+//and iter always use the detected size.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SegmentedSieve {
+    pub config: SieveConfig,
+}
+
+impl PrimeSieve for SegmentedSieve {
+    fn try_primes_up_to<T: PrimeInt>(&self, limit: usize) -> Result<Vec<T>, SieveError> {
+        Ok(try_sieve_segmented_with(limit, self.config)?.primes_result)
+    }
+
+    fn try_first_n_primes<T: PrimeInt>(&self, n: usize) -> Result<Vec<T>, SieveError> {
+        Ok(try_n_primes_segmented(n)?.primes_result)
+    }
+
+    fn try_count_up_to(&self, limit: usize) -> Result<u64, SieveError> {
+        try_count_primes_with(limit, self.config)
+    }
+
+    fn try_iter(&self, limit: usize) -> Result<Box<dyn Iterator<Item = usize>>, SieveError> {
+        Ok(Box::new(try_prime_iter(limit)?))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DynamicWheelSieve;
+
+impl PrimeSieve for DynamicWheelSieve {
+    fn try_primes_up_to<T: PrimeInt>(&self, limit: usize) -> Result<Vec<T>, SieveError> {
+        try_sieve_dynamic_wheel(limit)
+    }
+
+    fn try_first_n_primes<T: PrimeInt>(&self, n: usize) -> Result<Vec<T>, SieveError> {
+        try_n_primes_dynamic_wheel(n)
+    }
+
+    fn try_count_up_to(&self, limit: usize) -> Result<u64, SieveError> {
+        Ok(self.try_iter(limit)?.count() as u64)
+    }
+
+    fn try_iter(&self, limit: usize) -> Result<Box<dyn Iterator<Item = usize>>, SieveError> {
+        Ok(Box::new(dynamic_wheel().take_while(move |&p| p <= limit)))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LinearSieve;
+
+impl PrimeSieve for LinearSieve {
+    fn try_primes_up_to<T: PrimeInt>(&self, limit: usize) -> Result<Vec<T>, SieveError> {
+        try_sieve_linear(limit)
+    }
+
+    fn try_first_n_primes<T: PrimeInt>(&self, n: usize) -> Result<Vec<T>, SieveError> {
+        try_n_primes_linear(n)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AtkinSieve {
+    pub config: SieveConfig,
+}
+
+impl PrimeSieve for AtkinSieve {
+    fn try_primes_up_to<T: PrimeInt>(&self, limit: usize) -> Result<Vec<T>, SieveError> {
+        try_sieve_atkin_with(limit, self.config)
+    }
+
+    fn try_first_n_primes<T: PrimeInt>(&self, n: usize) -> Result<Vec<T>, SieveError> {
+        try_n_primes_atkin(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    test_utils::prime_sieve_tests! {
+        pritchard: PritchardSieve,
+        segmented: SegmentedSieve::default(),
+        segmented_small: SegmentedSieve { config: SieveConfig::detect().with_segment_bytes(8) },
+        dynamic_wheel: DynamicWheelSieve,
+        linear: LinearSieve,
+        atkin: AtkinSieve::default(),
+    }
+}
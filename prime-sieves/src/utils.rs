@@ -1,3 +1,6 @@
+use std::cmp::min;
+use std::fmt::Debug;
+
 pub fn isqrt(n: usize) -> usize {
     // Xₙ₊₁
     let mut x = n;
@@ -27,7 +30,49 @@ pub fn isqrt(n: usize) -> usize {
 
 }
 
+//Loose upper bound of the nth prime, n times its bit length.
+#[deprecated(note = "use bounds::nth_prime_upper, a proven and tighter bound")]
 pub fn approximate_primes(n: usize) -> usize {
     let approx_log = (usize::BITS - usize::leading_zeros(n)) as usize;
     approx_log * n
 }
+
+//Integer types sieves can output primes as, narrower types halve the
+//memory taken by the results as long as the limit fits them.
+pub trait PrimeInt: Copy + Ord + Debug + Send + Sync + 'static {
+    const MAX: u64;
+
+    //Truncating conversion, only called on values that fit.
+    fn from_usize(n: usize) -> Self;
+
+    fn as_usize(self) -> usize;
+
+    fn fits(n: usize) -> bool {
+        n as u64 <= Self::MAX
+    }
+
+    //Biggest usize that fits, the limit for sieves outputting Self.
+    fn max_usize() -> usize {
+        min(Self::MAX, usize::MAX as u64) as usize
+    }
+}
+
+macro_rules! prime_int {
+    ($($t:ty),*) => {
+        $(
+            impl PrimeInt for $t {
+                const MAX: u64 = <$t>::MAX as u64;
+
+                fn from_usize(n: usize) -> $t {
+                    n as $t
+                }
+
+                fn as_usize(self) -> usize {
+                    self as usize
+                }
+            }
+        )*
+    };
+}
+
+prime_int!(u32, u64, usize);
@@ -4,6 +4,10 @@ use std::time::SystemTime;
 
 use primal_sieve::Sieve;
 
+//For prime_sieve_tests, so callers need no primal-sieve of their own.
+#[doc(hidden)]
+pub use primal_sieve;
+
 pub const FIXED_SEED: [u8; 32] = [
     0x57, 0x4c, 0xbd, 0x96, 0x2d, 0x35, 0x60, 0x3e, 
     0x2c, 0x20, 0x91, 0x72, 0x05, 0xa3, 0xaf, 0xe1, 
@@ -45,3 +49,80 @@ pub fn dump(sieve: &Sieve, vec: Vec<usize>) {
     serde_json::to_writer_pretty(dump_expected, &sieve.primes_from(0).collect::<Vec<usize>>()).unwrap();
     serde_json::to_writer_pretty(dump_actual, &vec).unwrap();
 }
+
+//Generates a module of tests for each named PrimeSieve, checking all its
+//methods against primal, at every limit up to 1000 and a few past it.
+//The trait and SieveError have to be in scope where it's invoked,
+//test-utils can't name them without depending on prime-sieves.
+#[macro_export]
+macro_rules! prime_sieve_tests {
+    ($($name:ident: $sieve:expr),* $(,)?) => {
+        $(
+            mod $name {
+                use super::*;
+                use $crate::primal_sieve::Sieve;
+
+                const LIMITS: [usize; 6] = [1024, 65535, 65536, 100000, 1048576, 3000000];
+
+                #[test]
+                fn primes_up_to() {
+                    let primes = Sieve::new(3000000);
+                    let sieve = $sieve;
+                    for limit in (0..=1000).chain(LIMITS) {
+                        $crate::assert_primes(limit, &primes, |n| sieve.primes_up_to(n));
+                    }
+                }
+
+                #[test]
+                fn first_n_primes() {
+                    let primes = Sieve::new(3000000);
+                    let sieve = $sieve;
+                    for n in (0..=200).chain([303, 1229, 9592]) {
+                        $crate::assert_n(n, &primes, |n| sieve.first_n_primes(n));
+                    }
+                }
+
+                #[test]
+                fn count_up_to() {
+                    let primes = Sieve::new(3000000);
+                    let sieve = $sieve;
+                    for limit in (0..=1000).chain(LIMITS) {
+                        assert_eq!(sieve.count_up_to(limit), primes.prime_pi(limit) as u64);
+                    }
+                }
+
+                #[test]
+                fn iter() {
+                    let primes = Sieve::new(3000000);
+                    let sieve = $sieve;
+                    for limit in (0..=1000).chain(LIMITS) {
+                        let expected = primes.primes_from(0).take_while(|&p| p <= limit);
+                        assert!(sieve.iter(limit).eq(expected), "{}", limit);
+                    }
+                }
+
+                #[test]
+                fn narrow() {
+                    let sieve = $sieve;
+                    let wide: Vec<usize> = sieve.primes_up_to(20000);
+                    let narrow: Vec<u32> = sieve.primes_up_to(20000);
+                    assert!(narrow.iter().map(|&p| p as usize).eq(wide));
+
+                    let wide: Vec<usize> = sieve.first_n_primes(2000);
+                    let narrow: Vec<u32> = sieve.first_n_primes(2000);
+                    assert!(narrow.iter().map(|&p| p as usize).eq(wide));
+                }
+
+                #[test]
+                fn width_errors() {
+                    let sieve = $sieve;
+                    assert_eq!(
+                        sieve.try_primes_up_to::<u32>(u32::MAX as usize + 1),
+                        Err(SieveError::Overflow { what: "limit", value: u32::MAX as u64 + 1 })
+                    );
+                    assert_eq!(sieve.try_first_n_primes::<u32>(5), Ok(vec![2, 3, 5, 7, 11]));
+                }
+            }
+        )*
+    };
+}